@@ -0,0 +1,20 @@
+use std::env;
+
+fn main() {
+    // reported by the build script of ivory-sys from the php headers
+    let version_id: u32 = env::var("DEP_PHP7_VERSION_ID")
+        .expect("ivory-sys didn't report the php version")
+        .parse()
+        .expect("invalid php version");
+    let module_api = env::var("DEP_PHP7_MODULE_API").expect("ivory-sys didn't report the php module api");
+
+    println!("cargo:rustc-check-cfg=cfg(php8, php81)");
+    if version_id >= 80000 {
+        println!("cargo:rustc-cfg=php8");
+    }
+    if version_id >= 80100 {
+        println!("cargo:rustc-cfg=php81");
+    }
+    // php only loads extensions built for the same module api
+    println!("cargo:rustc-env=IVORY_BUILD_ID=API{},NTS", module_api);
+}
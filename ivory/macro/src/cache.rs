@@ -5,6 +5,8 @@ use crate::FunctionDefinition;
 
 lazy_static! {
     static ref FUNCTION_NAMES: Mutex<Vec<FunctionDefinition>> = Mutex::new(Vec::new());
+    static ref CONSTANT_TYPES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static ref ENUM_TYPES: Mutex<Vec<String>> = Mutex::new(Vec::new());
 }
 
 pub(crate) fn cache_function(func: FunctionDefinition) {
@@ -14,3 +16,19 @@ pub(crate) fn cache_function(func: FunctionDefinition) {
 pub(crate) fn get_functions() -> Vec<FunctionDefinition> {
     FUNCTION_NAMES.lock().unwrap().clone()
 }
+
+pub(crate) fn cache_constants(ty: String) {
+    CONSTANT_TYPES.lock().unwrap().push(ty);
+}
+
+pub(crate) fn get_constants() -> Vec<String> {
+    CONSTANT_TYPES.lock().unwrap().clone()
+}
+
+pub(crate) fn cache_enum(ty: String) {
+    ENUM_TYPES.lock().unwrap().push(ty);
+}
+
+pub(crate) fn get_enums() -> Vec<String> {
+    ENUM_TYPES.lock().unwrap().clone()
+}
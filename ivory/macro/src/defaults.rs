@@ -0,0 +1,111 @@
+use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use std::collections::HashMap;
+
+/// The attributes of the function arguments by argument name
+#[derive(Default)]
+pub(crate) struct ArgAttributes {
+    /// the values of `#[default = value]`
+    pub defaults: HashMap<String, TokenStream>,
+    /// other attributes like `#[allow(..)]`, these are applied to the argument in the generated wrapper
+    pub other: HashMap<String, Vec<TokenStream>>,
+}
+
+/// Remove the attributes from the function arguments
+///
+/// Attributes on function arguments can't be parsed by syn, so they are stripped before parsing the function.
+pub(crate) fn extract_arg_attributes(input: TokenStream) -> Result<(TokenStream, ArgAttributes), syn::Error> {
+    let mut attributes = ArgAttributes::default();
+    let mut seen_fn = false;
+    let mut done = false;
+
+    let mut output = Vec::new();
+    for token in input {
+        match token {
+            TokenTree::Ident(ref ident) if ident == "fn" => {
+                seen_fn = true;
+                output.push(token);
+            }
+            TokenTree::Group(ref group)
+                if seen_fn && !done && group.delimiter() == Delimiter::Parenthesis =>
+            {
+                done = true;
+                let stream = strip_arg_attributes(group.stream(), &mut attributes)?;
+                let mut stripped = Group::new(Delimiter::Parenthesis, stream);
+                stripped.set_span(group.span());
+                output.push(TokenTree::Group(stripped));
+            }
+            _ => output.push(token),
+        }
+    }
+
+    Ok((output.into_iter().collect(), attributes))
+}
+
+fn strip_arg_attributes(args: TokenStream, attributes: &mut ArgAttributes) -> Result<TokenStream, syn::Error> {
+    let mut output = Vec::new();
+    let mut pending_default = None;
+    let mut pending_other = Vec::new();
+    let mut tokens = args.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(ref punct) if punct.as_char() == '#' => {
+                let attribute = match tokens.next() {
+                    Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => {
+                        group
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            punct.span(),
+                            "invalid attribute on function argument",
+                        ))
+                    }
+                };
+                match parse_default(attribute.stream(), attribute.span())? {
+                    Some(default) => pending_default = Some(default),
+                    None => pending_other.push(vec![token.clone(), TokenTree::Group(attribute)].into_iter().collect()),
+                }
+            }
+            TokenTree::Ident(ref ident)
+                if ident != "mut" && (pending_default.is_some() || !pending_other.is_empty()) =>
+            {
+                if let Some(default) = pending_default.take() {
+                    attributes.defaults.insert(ident.to_string(), default);
+                }
+                if !pending_other.is_empty() {
+                    attributes.other.insert(ident.to_string(), pending_other.split_off(0));
+                }
+                output.push(token);
+            }
+            _ => output.push(token),
+        }
+    }
+
+    Ok(output.into_iter().collect())
+}
+
+/// Get the value of a `#[default = value]` attribute, `None` for other attributes
+fn parse_default(attribute: TokenStream, span: Span) -> Result<Option<TokenStream>, syn::Error> {
+    let mut tokens = attribute.into_iter();
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Ident(ref name)), Some(TokenTree::Punct(ref eq)))
+            if name == "default" && eq.as_char() == '=' =>
+        {
+            let value: TokenStream = tokens.collect();
+            if value.is_empty() {
+                Err(syn::Error::new(span, "expected a value, like #[default = 10]"))
+            } else {
+                Ok(Some(value))
+            }
+        }
+        (Some(TokenTree::Ident(ref name)), _) if name == "default" => {
+            Err(syn::Error::new(span, "expected a value, like #[default = 10]"))
+        }
+        // the arguments of the php function can't depend on the configuration
+        (Some(TokenTree::Ident(ref name)), _) if name == "cfg" || name == "cfg_attr" => Err(syn::Error::new(
+            span,
+            "conditional arguments are not supported for exported functions",
+        )),
+        _ => Ok(None),
+    }
+}
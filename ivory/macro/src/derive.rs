@@ -0,0 +1,620 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    parenthesized, parse_quote, Attribute, Data, DataEnum, DeriveInput, Expr, ExprLit, ExprUnary,
+    Fields, Ident, Lit, LitInt, Meta, NestedMeta, Token, Type, UnOp,
+};
+
+use crate::cache;
+
+pub(crate) fn is_php_attribute(attr: &Attribute) -> bool {
+    attr.path.segments.len() == 1 && attr.path.segments[0].ident == "php"
+}
+
+/// The items of all `#[php(..)]` attributes
+fn php_attributes(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut items = Vec::new();
+    for attr in attrs.iter().filter(|attr| is_php_attribute(attr)) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            _ => return Err(syn::Error::new_spanned(attr, "expected #[php(..)]")),
+        };
+        for item in list.nested {
+            match item {
+                NestedMeta::Meta(meta) => items.push(meta),
+                NestedMeta::Literal(lit) => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected #[php(name)] or #[php(name = value)]",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Options set on a field with `#[php(..)]`
+#[derive(Default)]
+struct FieldOptions {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+fn field_options(attrs: &[Attribute]) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for meta in php_attributes(attrs)? {
+        match meta {
+            Meta::Word(ref word) if word == "default" => options.default = true,
+            Meta::Word(ref word) if word == "skip" => options.skip = true,
+            Meta::NameValue(ref pair) if pair.ident == "rename" => match &pair.lit {
+                Lit::Str(name) => options.rename = Some(name.value()),
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "#[php(rename)] expects a string",
+                    ))
+                }
+            },
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "unsupported php attribute, expected rename, default or skip",
+                ))
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// The php value of an enum variant
+pub(crate) enum VariantValue {
+    String(String),
+    Long(i64),
+}
+
+impl VariantValue {
+    pub(crate) fn to_php_val(&self) -> TokenStream {
+        match self {
+            VariantValue::String(value) => quote!(::ivory::PhpVal::String(#value.to_string())),
+            VariantValue::Long(value) => {
+                let value = Literal::i64_unsuffixed(*value);
+                quote!(::ivory::PhpVal::Long(#value))
+            }
+        }
+    }
+}
+
+pub(crate) fn is_fieldless(data: &DataEnum) -> bool {
+    data.variants
+        .iter()
+        .all(|variant| matches!(variant.fields, Fields::Unit))
+}
+
+/// The variants of a union enum with the type they contain
+fn union_variants(data: &DataEnum) -> syn::Result<Vec<(Ident, Type)>> {
+    data.variants
+        .iter()
+        .map(|variant| match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Ok((variant.ident.clone(), fields.unnamed[0].ty.clone()))
+            }
+            _ => Err(syn::Error::new_spanned(
+                variant,
+                "enums with fields need exactly one unnamed field for every variant to be converted from and to php",
+            )),
+        })
+        .collect()
+}
+
+/// `#[php(value = ..)]` on an enum variant, parsed as an expression since meta items can't hold negative numbers
+struct VariantAttribute {
+    value: Expr,
+}
+
+impl Parse for VariantAttribute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        let name: Ident = content.parse()?;
+        if name != "value" {
+            return Err(syn::Error::new(name.span(), "expected value"));
+        }
+        content.parse::<Token![=]>()?;
+        let value = content.parse()?;
+        Ok(VariantAttribute { value })
+    }
+}
+
+/// The variants of a fieldless enum with their php values, variants without `#[php(value = ..)]` use their name
+pub(crate) fn variant_values(data: &DataEnum) -> syn::Result<Vec<(Ident, VariantValue)>> {
+    data.variants
+        .iter()
+        .map(|variant| {
+            let mut value = VariantValue::String(variant.ident.to_string());
+            for attr in variant.attrs.iter().filter(|attr| is_php_attribute(attr)) {
+                let expr = syn::parse2::<VariantAttribute>(attr.tts.clone())
+                    .map_err(|_| {
+                        syn::Error::new_spanned(attr, "unsupported php attribute, expected value")
+                    })?
+                    .value;
+                value = match expr {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(string),
+                        ..
+                    }) => VariantValue::String(string.value()),
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Int(int), ..
+                    }) => VariantValue::Long(variant_long(&int, false)?),
+                    Expr::Unary(ExprUnary {
+                        op: UnOp::Neg(_),
+                        expr,
+                        ..
+                    }) => match *expr {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Int(int), ..
+                        }) => VariantValue::Long(variant_long(&int, true)?),
+                        expr => {
+                            return Err(syn::Error::new_spanned(
+                                expr,
+                                "#[php(value)] expects a string or integer",
+                            ))
+                        }
+                    },
+                    expr => {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "#[php(value)] expects a string or integer",
+                        ))
+                    }
+                }
+            }
+            Ok((variant.ident.clone(), value))
+        })
+        .collect()
+}
+
+/// The value of an integer literal as php int, rejecting values that don't fit
+fn variant_long(int: &LitInt, negative: bool) -> syn::Result<i64> {
+    let value = int.value();
+    if negative && value == i64::max_value() as u64 + 1 {
+        Ok(i64::min_value())
+    } else if value <= i64::max_value() as u64 {
+        let value = value as i64;
+        Ok(if negative { -value } else { value })
+    } else {
+        Err(syn::Error::new_spanned(
+            int,
+            "#[php(value)] doesn't fit into a php int",
+        ))
+    }
+}
+
+/// The prefix for the constants of an enum if `#[php(constants)]` is set
+fn constant_prefix(input: &DeriveInput) -> syn::Result<Option<String>> {
+    let mut prefix = None;
+    for meta in php_attributes(&input.attrs)? {
+        match meta {
+            Meta::Word(ref word) if word == "constants" => {
+                prefix = Some(format!("{}_", constant_case(&input.ident.to_string())))
+            }
+            Meta::NameValue(ref pair) if pair.ident == "constants" => match &pair.lit {
+                Lit::Str(value) => prefix = Some(value.value()),
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "#[php(constants)] expects a string",
+                    ))
+                }
+            },
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "unsupported php attribute, expected constants",
+                ))
+            }
+        }
+    }
+    Ok(prefix)
+}
+
+/// Convert a CamelCase name to UPPER_SNAKE_CASE, keeping acronyms together (`HTTPStatus` becomes `HTTP_STATUS`)
+fn constant_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            let previous = chars[i - 1];
+            let acronym_end = previous.is_uppercase()
+                && chars.get(i + 1).map(|next| next.is_lowercase()) == Some(true);
+            if (!previous.is_uppercase() && previous != '_') || acronym_end {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_uppercase());
+    }
+    result
+}
+
+/// The named fields of the struct with their options
+fn struct_fields(input: &DeriveInput) -> syn::Result<Vec<(Ident, FieldOptions)>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| Ok((field.ident.clone().unwrap(), field_options(&field.attrs)?)))
+                .collect(),
+            fields => Err(syn::Error::new_spanned(
+                fields,
+                "only structs with named fields can be converted from and to php",
+            )),
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Whether `#[php(object)]` is set on a struct, which converts it into a `stdClass` object instead of an array
+fn is_object(input: &DeriveInput) -> syn::Result<bool> {
+    let mut object = false;
+    for meta in php_attributes(&input.attrs)? {
+        match meta {
+            Meta::Word(ref word) if word == "object" => object = true,
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "unsupported php attribute, expected object",
+                ))
+            }
+        }
+    }
+    Ok(object)
+}
+
+fn field_key(name: &Ident, options: &FieldOptions) -> String {
+    options.rename.clone().unwrap_or_else(|| name.to_string())
+}
+
+pub fn derive_from_php(mut input: DeriveInput) -> TokenStream {
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::ivory::FromPhpVal));
+    }
+
+    let result = match &input.data {
+        Data::Struct(_) => struct_from_php(&input),
+        Data::Enum(data) if is_fieldless(data) => enum_from_php(&input, data),
+        Data::Enum(data) => union_from_php(&input, data),
+        Data::Union(data) => Err(syn::Error::new(
+            data.union_token.span,
+            "unions can't be converted from php",
+        )),
+    };
+    result.unwrap_or_else(|err| err.to_compile_error())
+}
+
+pub fn derive_into_php(mut input: DeriveInput) -> TokenStream {
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::ivory::IntoPhpVal));
+    }
+
+    let result = match &input.data {
+        Data::Struct(_) => struct_into_php(&input),
+        Data::Enum(data) if is_fieldless(data) => enum_into_php(&input, data),
+        Data::Enum(data) => union_into_php(&input, data),
+        Data::Union(data) => Err(syn::Error::new(
+            data.union_token.span,
+            "unions can't be converted into php",
+        )),
+    };
+    result.unwrap_or_else(|err| err.to_compile_error())
+}
+
+fn struct_from_php(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let fields = struct_fields(input)?;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let field_inits = fields.iter().map(|(field, options)| {
+        let key = field_key(field, options);
+        if options.skip {
+            quote!(#field: Default::default())
+        } else if options.default {
+            quote!(#field: fields.take_or_default(#key)?)
+        } else {
+            quote!(#field: fields.take(#key)?)
+        }
+    });
+
+    let field_inits: Vec<TokenStream> = field_inits.collect();
+    let field_inits = &field_inits;
+
+    Ok(quote! {
+        impl #impl_generics ::ivory::FromPhpVal for #name #ty_generics #where_clause {
+            const TYPE_HINT: ::ivory::zend::ZValType = ::ivory::zend::ZValType::Array;
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                let mut fields = ::ivory::ArrayFields::new(val)?;
+                Ok(#name {
+                    #(#field_inits),*
+                })
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn coerce_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                let mut fields = ::ivory::ArrayFields::new(val)?.coercing();
+                Ok(#name {
+                    #(#field_inits),*
+                })
+            }
+        }
+    })
+}
+
+fn struct_into_php(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let fields = struct_fields(input)?;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let object = is_object(input)?;
+    let items = fields
+        .iter()
+        .filter(|(_, options)| !options.skip)
+        .map(|(field, options)| {
+            let key = field_key(field, options);
+            let key = if object {
+                quote!(#key.to_string())
+            } else {
+                quote!(::ivory::ArrayKey::String(#key.to_string()))
+            };
+            quote!((#key, ::ivory::IntoPhpVal::into_php_val(input.#field)))
+        });
+    let value = if object {
+        quote!(::ivory::PhpVal::Object(
+            vec![#(#items),*].into_iter().collect()
+        ))
+    } else {
+        quote!(::ivory::PhpVal::Array(vec![#(#items),*]))
+    };
+
+    Ok(quote! {
+        impl #impl_generics From<#name #ty_generics> for ::ivory::PhpVal #where_clause {
+            #[allow(unused_variables)]
+            fn from(input: #name #ty_generics) -> Self {
+                #value
+            }
+        }
+    })
+}
+
+fn enum_from_php(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let variants = variant_values(data)?;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let has_string = variants
+        .iter()
+        .any(|(_, value)| matches!(value, VariantValue::String(_)));
+    let has_long = variants
+        .iter()
+        .any(|(_, value)| matches!(value, VariantValue::Long(_)));
+    let type_hint = match (has_string, has_long) {
+        (true, false) => quote!(::ivory::zend::ZValType::String),
+        (false, true) => quote!(::ivory::zend::ZValType::Long),
+        _ => quote!(::ivory::zend::ZValType::Undef),
+    };
+
+    let arms = variants.iter().map(|(variant, value)| match value {
+        VariantValue::String(value) => {
+            quote!(::ivory::PhpVal::String(ref value) if value == #value => Ok(#name::#variant),)
+        }
+        VariantValue::Long(value) => {
+            let value = Literal::i64_unsuffixed(*value);
+            quote!(::ivory::PhpVal::Long(#value) => Ok(#name::#variant),)
+        }
+    });
+    let allowed = variants.iter().map(|(_, value)| value.to_php_val());
+    // values of the right type that don't match any variant
+    let mut value_patterns = Vec::new();
+    if has_string {
+        value_patterns.push(quote!(val @ ::ivory::PhpVal::String(_)));
+    }
+    if has_long {
+        value_patterns.push(quote!(val @ ::ivory::PhpVal::Long(_)));
+    }
+
+    // coerced values are matched against the variants after converting them to the type of the values
+    let coerce = match (has_string, has_long) {
+        (true, false) => Some(quote!(String)),
+        (false, true) => Some(quote!(i64)),
+        _ => None,
+    }
+    .map(|backing| {
+        quote! {
+            fn coerce_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                match <#backing as ::ivory::FromPhpVal>::coerce_php_val(val.clone()) {
+                    Ok(backing) => Self::from_php_val(backing.into()),
+                    Err(_) => Self::from_php_val(val),
+                }
+            }
+        }
+    });
+
+    let constants = constant_prefix(input)?.map(|prefix| {
+        cache::cache_constants(name.to_string());
+        let constants = variants.iter().map(|(variant, value)| {
+            let constant = format!("{}{}", prefix, constant_case(&variant.to_string()));
+            let value = value.to_php_val();
+            quote!((#constant.to_string(), #value))
+        });
+        quote! {
+            impl #impl_generics ::ivory::zend::PhpConstants for #name #ty_generics #where_clause {
+                fn constants() -> Vec<(String, ::ivory::PhpVal)> {
+                    vec![#(#constants),*]
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::ivory::FromPhpVal for #name #ty_generics #where_clause {
+            const TYPE_HINT: ::ivory::zend::ZValType = #type_hint;
+
+            fn from_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                match val.unwrap_reference() {
+                    #(#arms)*
+                    #(#value_patterns)|* => Err(::ivory::CastError::invalid_value(
+                        val.get_type(),
+                        vec![#(#allowed),*],
+                    )),
+                    val => Err(::ivory::CastError::new(val.get_type())),
+                }
+            }
+
+            #coerce
+        }
+
+        #constants
+    })
+}
+
+fn enum_into_php(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let variants = variant_values(data)?;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let arms = variants.iter().map(|(variant, value)| {
+        let value = value.to_php_val();
+        quote!(#name::#variant => #value,)
+    });
+
+    Ok(quote! {
+        impl #impl_generics From<#name #ty_generics> for ::ivory::PhpVal #where_clause {
+            fn from(input: #name #ty_generics) -> Self {
+                match input {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+/// Try to convert the value into each variant in order, remembering the first content error
+///
+/// With `exact` only the variants with a type hint matching the type of the value are tried.
+fn union_attempts(
+    name: &Ident,
+    variants: &[(Ident, Type)],
+    method: TokenStream,
+    exact: bool,
+) -> TokenStream {
+    let attempts = variants.iter().map(|(variant, ty)| {
+        let attempt = quote! {
+            match <#ty as ::ivory::FromPhpVal>::#method(val.clone()) {
+                Ok(inner) => return Ok(#name::#variant(inner)),
+                // the value has the type of the variant but its content didn't match
+                Err(err)
+                    if err.actual != actual
+                        || !err.path.is_empty()
+                        || !err.allowed.is_empty()
+                        || err.reason.is_some() =>
+                {
+                    content_error = content_error.or(Some(err));
+                }
+                Err(_) => {}
+            }
+        };
+        if exact {
+            quote! {
+                if <#ty as ::ivory::FromPhpVal>::TYPE_HINT == actual {
+                    #attempt
+                }
+            }
+        } else {
+            attempt
+        }
+    });
+    quote! {
+        #(#attempts)*
+        if let Some(err) = content_error {
+            return Err(err);
+        }
+    }
+}
+
+fn union_from_php(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let variants = union_variants(data)?;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // like php's union types, a variant matching the type exactly is preferred,
+    // so an int isn't taken by a float variant listed before an int variant
+    let exact_attempts = union_attempts(name, &variants, quote!(from_php_val), true);
+    let strict_attempts = union_attempts(name, &variants, quote!(from_php_val), false);
+    let coerce_attempts = union_attempts(name, &variants, quote!(coerce_php_val), false);
+    let types: Vec<TokenStream> = variants
+        .iter()
+        .map(|(_, ty)| quote!(<#ty as ::ivory::FromPhpVal>::TYPE_HINT))
+        .collect();
+    let types = &types;
+    let allow_null = variants
+        .iter()
+        .map(|(_, ty)| quote!(<#ty as ::ivory::FromPhpVal>::ALLOW_NULL));
+    let type_error = quote! {
+        let mut expected = Vec::new();
+        for ty in vec![#(#types),*] {
+            if ty != ::ivory::zend::ZValType::Undef && !expected.contains(&ty) {
+                expected.push(ty);
+            }
+        }
+        Err(::ivory::CastError::unexpected_type(actual, expected))
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::ivory::FromPhpVal for #name #ty_generics #where_clause {
+            const UNION_TYPES: &'static [::ivory::zend::ZValType] = &[#(#types),*];
+            const ALLOW_NULL: bool = false #(|| #allow_null)*;
+
+            fn from_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                let val = val.unwrap_reference();
+                let actual = val.get_type();
+                let mut content_error: Option<::ivory::CastError> = None;
+                #exact_attempts
+                #strict_attempts
+                #type_error
+            }
+
+            fn coerce_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                let val = val.unwrap_reference();
+                let actual = val.get_type();
+                let mut content_error: Option<::ivory::CastError> = None;
+                #exact_attempts
+                #strict_attempts
+                #coerce_attempts
+                #type_error
+            }
+        }
+    })
+}
+
+fn union_into_php(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let variants = union_variants(data)?;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let arms = variants.iter().map(
+        |(variant, _)| quote!(#name::#variant(inner) => ::ivory::IntoPhpVal::into_php_val(inner),),
+    );
+
+    Ok(quote! {
+        impl #impl_generics From<#name #ty_generics> for ::ivory::PhpVal #where_clause {
+            fn from(input: #name #ty_generics) -> Self {
+                match input {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
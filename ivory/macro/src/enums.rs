@@ -0,0 +1,164 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Ident};
+
+use crate::cache;
+use crate::derive::{is_fieldless, is_php_attribute, variant_values, VariantValue};
+
+pub fn ivory_enum(mut input: DeriveInput) -> TokenStream {
+    native_enum(&mut input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+/// The backing values of the cases, `None` for a pure enum without any `#[php(value = ..)]`
+fn backing_values(data: &DataEnum) -> syn::Result<Option<Vec<(Ident, VariantValue)>>> {
+    let has_value = |variant: &syn::Variant| variant.attrs.iter().any(is_php_attribute);
+    if !data.variants.iter().any(has_value) {
+        return Ok(None);
+    }
+    if let Some(variant) = data.variants.iter().find(|variant| !has_value(variant)) {
+        return Err(syn::Error::new_spanned(
+            variant,
+            "every case of a backed enum needs a #[php(value = ..)]",
+        ));
+    }
+
+    let values = variant_values(data)?;
+    for (index, (variant, value)) in values.iter().enumerate() {
+        let (_, first) = &values[0];
+        let same_type = matches!(
+            (first, value),
+            (VariantValue::String(_), VariantValue::String(_))
+                | (VariantValue::Long(_), VariantValue::Long(_))
+        );
+        if !same_type {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "the values of a backed enum need to be either all strings or all integers",
+            ));
+        }
+        let duplicate = values[..index]
+            .iter()
+            .any(|(_, other)| match (other, value) {
+                (VariantValue::String(a), VariantValue::String(b)) => a == b,
+                (VariantValue::Long(a), VariantValue::Long(b)) => a == b,
+                _ => false,
+            });
+        if duplicate {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "the values of a backed enum need to be unique",
+            ));
+        }
+    }
+    Ok(Some(values))
+}
+
+fn native_enum(input: &mut DeriveInput) -> syn::Result<TokenStream> {
+    let data = match &mut input.data {
+        Data::Enum(data) if is_fieldless(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[ivory_enum] only supports enums without fields",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "generic enums can't be registered as php enums",
+        ));
+    }
+
+    let backing_values = backing_values(data)?;
+    // the attributes are only read by this macro and would be unknown to the compiler
+    for variant in data.variants.iter_mut() {
+        variant.attrs.retain(|attr| !is_php_attribute(attr));
+    }
+
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let class_name = format!("{}\0", name_str);
+    let variants: Vec<&Ident> = data.variants.iter().map(|variant| &variant.ident).collect();
+    let case_names: Vec<String> = variants.iter().map(|variant| variant.to_string()).collect();
+    let from_arms = variants
+        .iter()
+        .zip(&case_names)
+        .map(|(variant, case)| quote!(#case => Ok(#name::#variant),));
+    let into_arms = variants
+        .iter()
+        .zip(&case_names)
+        .map(|(variant, case)| quote!(#name::#variant => #case,));
+    let case_values: Vec<TokenStream> = match &backing_values {
+        Some(values) => values
+            .iter()
+            .map(|(_, value)| {
+                let value = value.to_php_val();
+                quote!(Some(#value))
+            })
+            .collect(),
+        None => variants.iter().map(|_| quote!(None)).collect(),
+    };
+
+    cache::cache_enum(name_str.clone());
+
+    let cases = &case_names;
+    let php_enum = quote! {
+        impl ::ivory::zend::PhpEnum for #name {
+            const NAME: &'static str = #name_str;
+
+            fn cases() -> Vec<(&'static str, Option<::ivory::PhpVal>)> {
+                vec![#((#cases, #case_values)),*]
+            }
+        }
+    };
+
+    let from_php = quote! {
+        impl ::ivory::FromPhpVal for #name {
+            const TYPE_HINT: ::ivory::zend::ZValType = ::ivory::zend::ZValType::Object;
+            const CLASS_NAME: &'static str = #class_name;
+
+            fn from_php_val(val: ::ivory::PhpVal) -> Result<Self, ::ivory::CastError> {
+                match val.unwrap_reference() {
+                    // like php, class names are case insensitive
+                    ::ivory::PhpVal::EnumCase { ref class, ref case } if class.eq_ignore_ascii_case(#name_str) => {
+                        match case.as_str() {
+                            #(#from_arms)*
+                            case => Err(::ivory::CastError::invalid(
+                                ::ivory::zend::ZValType::Object,
+                                format!("{} is not a case of enum {}", case, #name_str),
+                            )),
+                        }
+                    }
+                    val => Err(::ivory::CastError::unexpected_class(val.get_type(), #name_str)),
+                }
+            }
+        }
+    };
+
+    let into_php = quote! {
+        impl From<#name> for ::ivory::PhpVal {
+            fn from(input: #name) -> Self {
+                let case = match input {
+                    #(#into_arms)*
+                };
+                ::ivory::PhpVal::EnumCase {
+                    class: #name_str.to_string(),
+                    case: case.to_string(),
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        #input
+
+        ::ivory::__require_php81!("#[ivory_enum]");
+
+        #php_enum
+
+        #from_php
+
+        #into_php
+    })
+}
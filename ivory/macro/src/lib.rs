@@ -3,11 +3,47 @@
 extern crate proc_macro;
 
 mod cache;
+mod defaults;
+mod derive;
+mod enums;
 
 use proc_macro2::{Span, TokenStream, TokenTree};
+use std::collections::HashMap;
+
+use crate::defaults::ArgAttributes;
 use quote::quote;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, AttributeArgs, FnArg, Ident, Item, ItemFn, LitStr, Pat, ReturnType, Type, parse_str};
+use syn::{
+    parse_macro_input, parse_str, AttributeArgs, DeriveInput, Expr, ExprLit, ExprUnary, FnArg, GenericArgument,
+    Ident, Item, ItemFn, Lit, LitStr, Pat, PathArguments, ReturnType, Type, UnOp,
+};
+
+/// Convert a struct from a php associative array, see the [crate documentation](index.html) for details
+#[proc_macro_derive(FromPhp, attributes(php))]
+pub fn derive_from_php(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::derive_from_php(input).into()
+}
+
+/// Convert a struct into a php associative array, see the [crate documentation](index.html) for details
+#[proc_macro_derive(IntoPhp, attributes(php))]
+pub fn derive_into_php(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::derive_into_php(input).into()
+}
+
+/// Register a fieldless enum as php 8.1 enum, see the [crate documentation](index.html) for details
+#[proc_macro_attribute]
+pub fn ivory_enum(attr: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if !attr.is_empty() {
+        let attr: TokenStream = attr.into();
+        return syn::Error::new_spanned(attr, "#[ivory_enum] doesn't take any arguments")
+            .to_compile_error()
+            .into();
+    }
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::ivory_enum(input).into()
+}
 
 /// See the [crate documentation](index.html) for details
 #[proc_macro_attribute]
@@ -16,12 +52,18 @@ pub fn ivory_export(
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     let input: TokenStream = input.into();
+    let (input, attributes) = match defaults::extract_arg_attributes(input) {
+        Ok(extracted) => extracted,
+        Err(err) => return err.to_compile_error().into(),
+    };
     let item = syn::parse2::<Item>(input).unwrap();
     let _attr = parse_macro_input!(attr as AttributeArgs);
 
     let output = match item {
-        Item::Fn(item_fn) => export_fn(item_fn).into(),
-        _ => unimplemented!(),
+        Item::Fn(item_fn) => export_fn(item_fn, attributes).into(),
+        item => syn::Error::new(item.span(), "only functions can be exported")
+            .to_compile_error()
+            .into(),
     };
 
     // panic!("{}", output);
@@ -34,54 +76,144 @@ pub(crate) struct ArgumentDefinition {
     name: String,
     ty: String,
     is_ref: bool,
+    is_variadic: bool,
+    is_optional: bool,
+    is_borrowed: bool,
+    /// `ZArray` arguments borrow the array from the engine without converting it
+    is_array_view: bool,
+    /// the default value of an optional argument as php code, for the stubs
+    default: Option<String>,
 }
 
 #[derive(Clone)]
 pub(crate) struct FunctionDefinition {
     name: String,
     args: Vec<ArgumentDefinition>,
+    /// types `T` used as `Resource<T>` in the arguments or return type
+    resources: Vec<String>,
 }
 
-fn export_fn(item: ItemFn) -> TokenStream {
+fn export_fn(item: ItemFn, attributes: ArgAttributes) -> TokenStream {
+    let ArgAttributes { mut defaults, other } = attributes;
     let span = item.span();
     let name = item.ident;
     let name_str = name.to_string();
     let body = item.block;
     let decl = item.decl;
     if decl.generics.gt_token.is_some() {
-        unimplemented!("generics are not supported for exported functions");
+        return syn::Error::new_spanned(&decl.generics, "generics are not supported for exported functions")
+            .to_compile_error();
     }
 
-    let args: Vec<(ArgumentDefinition, Type)> = decl.inputs.into_iter().map(get_arg_info).collect();
-    let arg_defs: Vec<ArgumentDefinition> = args.clone().into_iter().map(|(def, _)| def).collect();
-    let func_def = FunctionDefinition {
-        name: name_str.clone(),
-        args: arg_defs,
+    let args: Vec<(ArgumentDefinition, Type)> = match decl
+        .inputs
+        .into_iter()
+        .map(|arg| get_arg_info(arg, &defaults))
+        .collect()
+    {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error(),
     };
-    cache::cache_function(func_def);
+    let is_variadic = args.iter().any(|(arg, _)| arg.is_variadic);
+    if let Some((_, ty)) = args.iter().rev().skip(1).find(|(arg, _)| arg.is_variadic) {
+        return syn::Error::new_spanned(ty, "only the last argument of an exported function can be variadic")
+            .to_compile_error();
+    }
+    let arg_defs: Vec<ArgumentDefinition> = args.clone().into_iter().map(|(def, _)| def).collect();
     let arg_count = args.len() as u32;
+    // arguments are converted using coercive typing unless the caller declared strict_types
+    let strict = if args.iter().any(|(arg, _)| !arg.is_array_view) {
+        quote!(let strict = data.is_strict_types();)
+    } else {
+        quote!()
+    };
 
-    let arg_cast = args.iter().enumerate().map(|(_index, (arg, ty))| {
+    let arg_cast = args.iter().enumerate().map(|(index, (arg, ty))| {
         let arg_ident = Ident::new(&arg.name, span);
-        quote!(
-            let #arg_ident: #ty = {
-                let result: Result<#ty, ::ivory::CastError> = args.next().unwrap().into();
-                match result {
+        let index = index as u32;
+        let attrs = other.get(&arg.name).cloned().unwrap_or_default();
+        let attrs = &attrs;
+        if arg.is_variadic {
+            return quote!(
+                #(#attrs)* let #arg_ident: #ty = match <#ty>::from_args(data.args().skip(#index as usize), strict) {
                     Ok(val) => val,
                     Err(err) => {
-                        ::ivory::externs::error(::ivory::externs::ErrorLevel::Error, format!("{}", err));
+                        ::ivory::externs::throw_type_error(format!("{}", err));
                         return;
                     }
+                };
+            );
+        }
+        if arg.is_array_view {
+            return quote!(
+                #(#attrs)* let #arg_ident: #ty = match ::ivory::zend::ZArray::from_arg(data.arg(#index)) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        ::ivory::externs::throw_type_error(format!("{}", err));
+                        return;
+                    }
+                };
+            );
+        }
+        if let Type::Reference(reference) = ty {
+            // borrowed arguments are created by `FromArg` and stored in the wrapper
+            let value_ident = Ident::new(&format!("{}_value", arg.name), span);
+            let inner = &reference.elem;
+            let borrow = match reference.mutability {
+                Some(_) => quote!(&mut #value_ident),
+                None => quote!(&#value_ident),
+            };
+            return quote!(
+                let mut #value_ident = match <#inner as ::ivory::FromArg>::from_arg(data.arg(#index), strict) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        ::ivory::externs::throw_type_error(format!("{}", err));
+                        return;
+                    }
+                };
+                #(#attrs)* let #arg_ident: #ty = #borrow;
+            );
+        }
+        let missing = match defaults.remove(&arg.name) {
+            Some(default) => match syn::parse2::<LitStr>(default.clone()) {
+                Ok(lit_str) if is_type_named(ty, "Nullable") => {
+                    quote!(::ivory::Nullable::from(#lit_str.to_string()))
                 }
+                Ok(lit_str) => quote!(#lit_str.into()),
+                Err(_) if is_type_named(ty, "Nullable") => quote!(::ivory::Nullable::from(#default)),
+                Err(_) => default,
+            },
+            // missing arguments are passed as undefined so optional arguments can handle them
+            None => cast_arg(quote!(<#ty as ::ivory::FromPhpVal>::from_php_val(::ivory::PhpVal::Undef))),
+        };
+        let cast = cast_arg(quote!(zval.cast::<#ty>(strict)));
+        quote!(
+            #(#attrs)* let #arg_ident: #ty = match data.arg(#index) {
+                Some(zval) => #cast,
+                None => #missing,
             };
         )
     });
 
     let return_type = decl.output;
+    let mut resources = Vec::new();
+    for (_, ty) in &args {
+        find_resources(ty, &mut resources);
+    }
     let return_type = match return_type {
         ReturnType::Default => quote!(),
-        ReturnType::Type(_, return_type) => quote!(: #return_type),
+        ReturnType::Type(_, return_type) => {
+            find_resources(&return_type, &mut resources);
+            quote!(: #return_type)
+        }
+    };
+
+    let func_def = FunctionDefinition {
+        name: name_str.clone(),
+        args: arg_defs,
+        resources,
     };
+    cache::cache_function(func_def);
 
     quote! {
         #[no_mangle]
@@ -89,41 +221,276 @@ fn export_fn(item: ItemFn) -> TokenStream {
             let data: &::ivory::zend::ExecuteData = unsafe { data.as_ref() }.unwrap();
             // the less than case is handled during argument casting
             // this is needed for optional arguments
-            if data.num_args() > #arg_count {
+            if !#is_variadic && data.num_args() > #arg_count {
                 ::ivory::externs::error(::ivory::externs::ErrorLevel::Error, format!("unexpected number of arguments, expected {}, got {}", #arg_count, data.num_args()));
                 return;
             }
-            let mut args = data.args();
+            #strict
             #(#arg_cast);*
             let result #return_type = #body;
 
-            let php_val = ::ivory::PhpVal::from(result);
-            let zval = ::ivory::zend::ZVal::from(php_val);
+            let zval = ::ivory::zend::IntoZVal::into_zval(result);
             unsafe {
-                *retval = zval
+                // the return value is handed over to php
+                ::std::ptr::write(retval, zval);
             };
         }
     }
 }
 
-fn get_arg_info(arg: FnArg) -> (ArgumentDefinition, Type) {
+fn cast_arg(cast: TokenStream) -> TokenStream {
+    quote!({
+        match #cast {
+            Ok(val) => val,
+            Err(err) => {
+                ::ivory::externs::throw_type_error(format!("{}", err));
+                return;
+            }
+        }
+    })
+}
+
+fn get_arg_info(
+    arg: FnArg,
+    defaults: &HashMap<String, TokenStream>,
+) -> Result<(ArgumentDefinition, Type), syn::Error> {
     match arg {
         FnArg::Captured(cap) => {
             let arg_type = cap.ty;
             match cap.pat {
-                Pat::Ident(ident_pat) => (
-                    ArgumentDefinition {
-                        name: ident_pat.ident.to_string(),
-                        ty: format!("{}", quote!(#arg_type)),
-                        is_ref: ident_pat.by_ref.is_some(),
-                    },
-                    arg_type,
-                ),
-                Pat::Ref(_ref_pat) => unimplemented!(),
-                _ => panic!(),
+                Pat::Ident(ident_pat) => {
+                    let name = ident_pat.ident.to_string();
+                    let is_variadic = is_type_named(&arg_type, "Variadic");
+                    let ref_inner = php_ref_inner(&arg_type);
+                    let is_optional = is_variadic
+                        || defaults.contains_key(&name)
+                        || is_type_named(ref_inner.unwrap_or(&arg_type), "Option")
+                        || is_type_named(&arg_type, "Optional");
+                    let is_borrowed = matches!(arg_type, Type::Reference(_));
+                    let is_array_view = is_type_named(&arg_type, "ZArray");
+                    let default = match defaults.get(&name) {
+                        Some(default) => Some(php_default(default)),
+                        None if is_optional && !is_variadic => {
+                            Some(optional_default(ref_inner.unwrap_or(&arg_type)))
+                        }
+                        None => None,
+                    };
+                    Ok((
+                        ArgumentDefinition {
+                            name,
+                            ty: format!("{}", quote!(#arg_type)),
+                            is_ref: ref_inner.is_some() || is_hash_table(&arg_type),
+                            is_variadic,
+                            is_optional,
+                            is_borrowed,
+                            is_array_view,
+                            default,
+                        },
+                        arg_type,
+                    ))
+                }
+                pat => Err(syn::Error::new(
+                    pat.span(),
+                    "only plain identifiers are supported as argument patterns of exported functions",
+                )),
+            }
+        }
+        arg => Err(syn::Error::new(arg.span(), "only normal function arguments are supported")),
+    }
+}
+
+/// The declared type of an argument, see `FromPhpVal` for the meaning of each part
+struct ArgTypeHint {
+    ty: TokenStream,
+    allow_null: TokenStream,
+    union_types: TokenStream,
+    class_name: TokenStream,
+}
+
+/// The type hint of an argument and whether it accepts `null`
+fn arg_type_hint(arg: &ArgumentDefinition) -> ArgTypeHint {
+    let ty = parse_str::<Type>(&arg.ty).unwrap();
+    let untyped = |ty| ArgTypeHint {
+        ty,
+        allow_null: quote!(false),
+        union_types: quote!(&[]),
+        class_name: quote!(""),
+    };
+    if arg.is_array_view || is_hash_table(&ty) {
+        untyped(quote!(::ivory::zend::ZValType::Array))
+    } else if arg.is_variadic || arg.is_borrowed {
+        untyped(quote!(::ivory::zend::ZValType::Undef))
+    } else {
+        // the type hint is taken from the `FromPhpVal` implementation of the argument
+        ArgTypeHint {
+            ty: quote!(<#ty as ::ivory::FromPhpVal>::TYPE_HINT),
+            allow_null: quote!(<#ty as ::ivory::FromPhpVal>::ALLOW_NULL),
+            union_types: quote!(<#ty as ::ivory::FromPhpVal>::UNION_TYPES),
+            class_name: quote!(<#ty as ::ivory::FromPhpVal>::CLASS_NAME),
+        }
+    }
+}
+
+/// The `php_stubs()` function declaring the exported functions and enums in php
+fn get_stubs(funcs: &[FunctionDefinition], enums: &[Type]) -> TokenStream {
+    let functions = funcs.iter().map(|func| {
+        let name = &func.name;
+        let args = func.args.iter().map(|arg| {
+            let name = &arg.name;
+            let ArgTypeHint {
+                ty,
+                allow_null,
+                union_types,
+                class_name,
+            } = arg_type_hint(arg);
+            let is_ref = arg.is_ref;
+            let is_variadic = arg.is_variadic;
+            let default = match &arg.default {
+                Some(default) => quote!(Some(#default)),
+                None => quote!(None),
+            };
+            quote!(::ivory::stubs::StubArg {
+                name: #name,
+                ty: #ty,
+                class_name: #class_name,
+                union_types: #union_types,
+                allow_null: #allow_null,
+                by_reference: #is_ref,
+                is_variadic: #is_variadic,
+                default: #default,
+            })
+        });
+        quote!(::ivory::stubs::StubFunction {
+            name: #name,
+            args: &[#(#args),*],
+        })
+    });
+
+    quote! {
+        /// Php stubs declaring the exported functions and enums, including the default values of optional arguments
+        pub fn php_stubs() -> String {
+            ::ivory::stubs::render_stubs(
+                &[#(#functions),*],
+                &[#(::ivory::stubs::StubEnum::of::<#enums>()),*],
+            )
+        }
+    }
+}
+
+/// Render the default value of an argument as php code, `UNKNOWN` for values that can't be expressed in php
+fn php_default(default: &TokenStream) -> String {
+    let (negative, expr) = match syn::parse2::<Expr>(default.clone()) {
+        Ok(Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_), expr, ..
+        })) => (true, *expr),
+        Ok(expr) => (false, expr),
+        Err(_) => return "UNKNOWN".to_string(),
+    };
+    let sign = if negative { "-" } else { "" };
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Int(int) => format!("{}{}", sign, int.value()),
+            Lit::Float(float) => {
+                let value = format!("{:?}", float.value());
+                format!("{}{}", sign, value)
+            }
+            Lit::Str(string) if !negative => format!("'{}'", string.value().replace('\\', "\\\\").replace('\'', "\\'")),
+            Lit::Bool(boolean) if !negative => boolean.value.to_string(),
+            _ => "UNKNOWN".to_string(),
+        },
+        Expr::Path(ref path) if !negative && path.path.is_ident("None") => "null".to_string(),
+        _ => "UNKNOWN".to_string(),
+    }
+}
+
+/// The default value of an optional argument without `#[default = value]` as php code
+fn optional_default(ty: &Type) -> String {
+    let accepts_null = is_type_named(ty, "Option")
+        || generic_inner(ty, "Optional").map(|inner| is_type_named(inner, "Nullable")) == Some(true);
+    if accepts_null {
+        "null".to_string()
+    } else {
+        // like php's own stubs for arguments that can only be omitted
+        "UNKNOWN".to_string()
+    }
+}
+
+/// Check if the last segment of a type path matches the name
+fn is_type_named(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .iter()
+            .last()
+            .map(|segment| segment.ident == name)
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Check if the type is `&mut ZHashTable`, which passes an array by reference
+fn is_hash_table(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => {
+            is_type_named(&reference.elem, "ZHashTable")
+        }
+        _ => false,
+    }
+}
+
+/// Get the inner type `T` of a `&mut PhpRef<T>` argument
+fn php_ref_inner(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => {
+            generic_inner(&reference.elem, "PhpRef")
+        }
+        _ => None,
+    }
+}
+
+/// Get the first generic argument of a type path if the last segment matches the name
+fn generic_inner<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.iter().last()?,
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.iter().next()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Collect the inner types of all `Resource<T>` types used in a type
+fn find_resources(ty: &Type, resources: &mut Vec<String>) {
+    match ty {
+        Type::Reference(reference) => find_resources(&reference.elem, resources),
+        Type::Tuple(tuple) => {
+            for elem in &tuple.elems {
+                find_resources(elem, resources);
+            }
+        }
+        Type::Path(path) => {
+            for segment in &path.path.segments {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        if let GenericArgument::Type(inner) = arg {
+                            if segment.ident == "Resource" {
+                                resources.push(format!("{}", quote!(#inner)));
+                            }
+                            find_resources(inner, resources);
+                        }
+                    }
+                }
             }
         }
-        _ => panic!("only normal function arguments are supported"),
+        _ => {}
     }
 }
 
@@ -142,7 +509,32 @@ pub fn ivory_module(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     let fields = group.stream();
 
-    let funcs = get_funcs(cache::get_functions(), span);
+    let functions = cache::get_functions();
+    let mut resources: Vec<String> = functions
+        .iter()
+        .flat_map(|func| func.resources.iter().cloned())
+        .collect();
+    resources.sort();
+    resources.dedup();
+    let resources = resources
+        .iter()
+        .map(|ty| parse_str::<Type>(ty).unwrap());
+
+    let mut constants = cache::get_constants();
+    constants.sort();
+    constants.dedup();
+    let constants = constants
+        .iter()
+        .map(|ty| parse_str::<Type>(ty).unwrap());
+
+    let mut enums = cache::get_enums();
+    enums.sort();
+    enums.dedup();
+    let enums: Vec<Type> = enums.iter().map(|ty| parse_str::<Type>(ty).unwrap()).collect();
+    let enums = &enums;
+
+    let stubs = get_stubs(&functions, enums);
+    let funcs = get_funcs(functions, span);
 
     let fields = into_c_str(fields);
 
@@ -155,12 +547,29 @@ pub fn ivory_module(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             ::ivory::info::php_print_module_info(&MODULE_INFO.info);
         }
 
+        extern "C" fn php_module_startup(_type: ::std::os::raw::c_int, module_number: ::std::os::raw::c_int) -> ::std::os::raw::c_int {
+            ::ivory::zend::startup_module(MODULE_INFO.name, module_number);
+            #(::ivory::zend::register_resource::<#resources>(module_number);)*
+            #(::ivory::zend::register_constants::<#constants>(module_number);)*
+            #(::ivory::zend::register_enum::<#enums>(module_number);)*
+            0
+        }
+
+        extern "C" fn php_module_shutdown(_type: ::std::os::raw::c_int, module_number: ::std::os::raw::c_int) -> ::std::os::raw::c_int {
+            ::ivory::zend::shutdown_module(module_number);
+            0
+        }
+
         #funcs
 
+        #stubs
+
         #[no_mangle]
         pub extern "C" fn get_module() -> *mut ::ivory::zend::ModuleInternal {
             let mut entry = Box::new(::ivory::zend::ModuleInternal::new(MODULE_INFO.name, MODULE_INFO.version));
 
+            entry.set_startup_func(php_module_startup);
+            entry.set_shutdown_func(php_module_shutdown);
             entry.set_info_func(php_module_info);
 
 
@@ -212,11 +621,32 @@ fn get_funcs(funcs: Vec<FunctionDefinition>, span: Span) -> TokenStream {
         let name = func.name;
         let name_ident = Ident::new(&name, span.clone());
         let num_args = func.args.len();
+        // php considers every argument after the required count as optional
+        let required_args = func
+            .args
+            .iter()
+            .rposition(|arg| !arg.is_optional)
+            .map(|index| index + 1)
+            .unwrap_or(0);
         let arg_defs = func.args.iter().map(|arg| {
             let name = &arg.name;
             let is_ref = &arg.is_ref;
-            let ty = parse_str::<Type>(&arg.ty).unwrap();
-            quote!(::ivory::zend::ArgInfo::from_type::<#ty>(::ivory::c_str!(#name), #is_ref))
+            let is_variadic = &arg.is_variadic;
+            let ArgTypeHint {
+                ty,
+                allow_null,
+                union_types,
+                class_name,
+            } = arg_type_hint(arg);
+            quote!(::ivory::zend::ArgInfo::typed(
+                ::ivory::c_str!(#name),
+                #ty,
+                #class_name,
+                #union_types,
+                #allow_null,
+                #is_variadic,
+                #is_ref
+            ))
         });
 
         if num_args > 0 {
@@ -224,7 +654,7 @@ fn get_funcs(funcs: Vec<FunctionDefinition>, span: Span) -> TokenStream {
                 ::ivory::zend::Function::new_with_args(
                     {concat!(#name, "\0").as_ptr() as *const ::std::os::raw::c_char},
                     #name_ident as *const ::std::os::raw::c_void,
-                    &[::ivory::zend::ArgInfo::arg_count(#num_args),
+                    &[::ivory::zend::ArgInfo::arg_count(#required_args),
                         #(#arg_defs),*
                     ],
                     #num_args as u32
@@ -0,0 +1,229 @@
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+
+use ivory_sys::zval;
+
+use crate::zend::{ZVal, ZValType};
+use crate::{CastError, FromPhpVal, IntoPhpVal, PhpVal};
+
+/// Conversion for arguments that are borrowed by exported functions
+///
+/// An exported function taking a `&T` or `&mut T` argument receives a reference to the value created by `from_arg`,
+/// `arg` is `None` if the argument was omitted and `strict` is set if the caller declared `strict_types`.
+pub trait FromArg: Sized {
+    fn from_arg(arg: Option<&ZVal>, strict: bool) -> Result<Self, CastError>;
+}
+
+/// Collects all remaining arguments of a variadic php function
+///
+/// Can only be used as the last argument of an exported function
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variadic<T>(pub Vec<T>);
+
+impl<T: FromPhpVal> Variadic<T> {
+    /// Convert the remaining arguments, `strict` disables php's coercive typing
+    pub fn from_args<'a, I: Iterator<Item = &'a ZVal>>(args: I, strict: bool) -> Result<Self, CastError> {
+        args.map(|arg| arg.cast(strict))
+            .collect::<Result<Vec<T>, CastError>>()
+            .map(Variadic)
+    }
+}
+
+impl<T> Variadic<T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Variadic<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Variadic<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> IntoIterator for Variadic<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// An argument that can be omitted but doesn't accept `null`
+///
+/// Unlike `Option<T>`, passing `null` is converted by `T`, use `Optional<Nullable<T>>` to distinguish between
+/// an omitted argument and `null`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Optional<T> {
+    Omitted,
+    Given(T),
+}
+
+impl<T> Optional<T> {
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Optional::Omitted => None,
+            Optional::Given(value) => Some(value),
+        }
+    }
+
+    pub fn unwrap_or(self, default: T) -> T {
+        self.into_option().unwrap_or(default)
+    }
+}
+
+impl<T: FromPhpVal> FromPhpVal for Optional<T> {
+    const TYPE_HINT: ZValType = T::TYPE_HINT;
+    const ALLOW_NULL: bool = T::ALLOW_NULL;
+    const UNION_TYPES: &'static [ZValType] = T::UNION_TYPES;
+    const CLASS_NAME: &'static str = T::CLASS_NAME;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val {
+            PhpVal::Undef => Ok(Optional::Omitted),
+            val => T::from_php_val(val).map(Optional::Given),
+        }
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val {
+            PhpVal::Undef => Ok(Optional::Omitted),
+            val => T::coerce_php_val(val).map(Optional::Given),
+        }
+    }
+}
+
+/// An argument that accepts `null` but can't be omitted
+///
+/// Combined with a default value, `#[default = 5] x: Nullable<i64>` is the equivalent of `?int $x = 5`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Nullable<T> {
+    Null,
+    Value(T),
+}
+
+impl<T> Nullable<T> {
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Nullable::Null => None,
+            Nullable::Value(value) => Some(value),
+        }
+    }
+}
+
+impl<T> From<T> for Nullable<T> {
+    fn from(value: T) -> Self {
+        Nullable::Value(value)
+    }
+}
+
+impl<T: FromPhpVal> FromPhpVal for Nullable<T> {
+    const TYPE_HINT: ZValType = T::TYPE_HINT;
+    const ALLOW_NULL: bool = true;
+    const UNION_TYPES: &'static [ZValType] = T::UNION_TYPES;
+    const CLASS_NAME: &'static str = T::CLASS_NAME;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val.unwrap_reference() {
+            PhpVal::Null => Ok(Nullable::Null),
+            val => T::from_php_val(val).map(Nullable::Value),
+        }
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val.unwrap_reference() {
+            PhpVal::Null => Ok(Nullable::Null),
+            val => T::coerce_php_val(val).map(Nullable::Value),
+        }
+    }
+}
+
+impl<T: IntoPhpVal> From<Nullable<T>> for PhpVal {
+    fn from(input: Nullable<T>) -> Self {
+        match input {
+            Nullable::Null => PhpVal::Null,
+            Nullable::Value(value) => value.into_php_val(),
+        }
+    }
+}
+
+/// An argument passed by reference
+///
+/// Taking a `&mut PhpRef<T>` argument marks the argument as by-reference for php,
+/// any changes made to the value are written back into the variable of the caller.
+///
+/// A variable that is undefined or `null` is only converted if `T` accepts `null`, otherwise the `PhpRef` has
+/// no value until it is `set`, like the `$matches` of `preg_match`.
+/// Dereferencing a `PhpRef` without a value panics, use `get` if the variable might not have one.
+pub struct PhpRef<T: IntoPhpVal> {
+    target: Option<*mut zval>,
+    value: Option<T>,
+    modified: bool,
+}
+
+impl<T: FromPhpVal + IntoPhpVal> FromArg for PhpRef<T> {
+    fn from_arg(arg: Option<&ZVal>, strict: bool) -> Result<Self, CastError> {
+        let target = arg.and_then(ZVal::reference_target);
+        let val = match target {
+            Some(target) => Some(unsafe { ZVal::from_raw(target) }),
+            None => arg,
+        };
+        let value = match val.map(ZVal::deref_indirect) {
+            Some(val) if !matches!(val.get_type(), ZValType::Undef | ZValType::Null) => Some(val.cast(strict)?),
+            Some(val) => T::from_php_val(val.as_php_val()).ok(),
+            None => T::from_php_val(PhpVal::Undef).ok(),
+        };
+        Ok(PhpRef {
+            target,
+            value,
+            modified: false,
+        })
+    }
+}
+
+impl<T: IntoPhpVal> PhpRef<T> {
+    /// The current value, `None` if the variable had no value and nothing was set yet
+    pub fn get(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    pub fn set(&mut self, value: T) {
+        self.value = Some(value);
+        self.modified = true;
+    }
+}
+
+impl<T: IntoPhpVal> Deref for PhpRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value.as_ref().expect("the variable passed by reference has no value")
+    }
+}
+
+impl<T: IntoPhpVal> DerefMut for PhpRef<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
+        self.value.as_mut().expect("the variable passed by reference has no value")
+    }
+}
+
+impl<T: IntoPhpVal> Drop for PhpRef<T> {
+    fn drop(&mut self) {
+        // when php passed the argument by value there is nothing to write back to
+        if let (Some(target), Some(value), true) = (self.target, self.value.take(), self.modified) {
+            let new_value = ZVal::from(value.into_php_val());
+            let old_value = unsafe { ptr::replace(target, new_value.into_raw()) };
+            drop(ZVal::from(old_value));
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::iter::FromIterator;
+
+use indexmap::map::{IntoIter, Iter, IterMut};
+use indexmap::IndexMap;
+
+use crate::zend::ZValType;
+use crate::{ArrayKey, CastError, FromPhpVal, IntoPhpVal, PhpVal};
+
+/// An ordered php array with constant time key lookup
+///
+/// Like php's `HashTable`, elements keep their insertion order, every key occurs only once
+/// and `push` appends using the next free integer index.
+/// String keys containing a canonical integer are converted to integer keys, including `ArrayKey::String` keys.
+#[derive(Debug, Clone, Default)]
+pub struct PhpArray {
+    items: IndexMap<ArrayKey, PhpVal>,
+    next_index: i64,
+}
+
+impl PhpArray {
+    pub fn new() -> Self {
+        PhpArray::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        PhpArray {
+            items: IndexMap::with_capacity(capacity),
+            next_index: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get<K: Into<ArrayKey>>(&self, key: K) -> Option<&PhpVal> {
+        self.items.get(&key.into().normalize())
+    }
+
+    pub fn get_mut<K: Into<ArrayKey>>(&mut self, key: K) -> Option<&mut PhpVal> {
+        self.items.get_mut(&key.into().normalize())
+    }
+
+    pub fn contains_key<K: Into<ArrayKey>>(&self, key: K) -> bool {
+        self.items.contains_key(&key.into().normalize())
+    }
+
+    /// Set the value of a key, returning the previous value
+    ///
+    /// An existing key keeps its position, a new key is added at the end.
+    pub fn insert<K: Into<ArrayKey>, T: IntoPhpVal>(&mut self, key: K, value: T) -> Option<PhpVal> {
+        let key = key.into().normalize();
+        if let ArrayKey::Int(index) = key {
+            if index >= self.next_index {
+                self.next_index = index.saturating_add(1);
+            }
+        }
+        self.items.insert(key, value.into_php_val())
+    }
+
+    /// Remove a key, the order of the remaining elements is kept
+    ///
+    /// Like php, the next free index isn't lowered when removing the last integer key.
+    pub fn remove<K: Into<ArrayKey>>(&mut self, key: K) -> Option<PhpVal> {
+        self.items.shift_remove(&key.into().normalize())
+    }
+
+    /// Append a value using the next free integer index, the same as `$array[] = $value`
+    ///
+    /// Returns the index of the new element or `None` if the largest possible index is already used.
+    pub fn push<T: IntoPhpVal>(&mut self, value: T) -> Option<i64> {
+        let index = self.next_index;
+        if self.items.contains_key(&ArrayKey::Int(index)) {
+            return None;
+        }
+        self.insert(index, value);
+        Some(index)
+    }
+
+    /// The index that will be used by the next `push`
+    pub fn next_index(&self) -> i64 {
+        self.next_index
+    }
+
+    /// Whether the keys are consecutive integers starting from 0, in order
+    pub fn is_list(&self) -> bool {
+        self.items
+            .keys()
+            .enumerate()
+            .all(|(index, key)| *key == ArrayKey::Int(index as i64))
+    }
+
+    pub fn iter(&self) -> Iter<'_, ArrayKey, PhpVal> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, ArrayKey, PhpVal> {
+        self.items.iter_mut()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &ArrayKey> {
+        self.items.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &PhpVal> {
+        self.items.values()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.next_index = 0;
+    }
+}
+
+/// Arrays are equal if they contain the same keys and values in the same order, like php's `===`
+///
+/// The next free index isn't compared, an array that had elements removed equals one that never had them.
+impl PartialEq for PhpArray {
+    fn eq(&self, other: &Self) -> bool {
+        self.items.len() == other.items.len() && self.items.iter().eq(other.items.iter())
+    }
+}
+
+impl IntoIterator for PhpArray {
+    type Item = (ArrayKey, PhpVal);
+    type IntoIter = IntoIter<ArrayKey, PhpVal>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PhpArray {
+    type Item = (&'a ArrayKey, &'a PhpVal);
+    type IntoIter = Iter<'a, ArrayKey, PhpVal>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// Later values overwrite earlier values with the same key, like php array literals
+impl<K: Into<ArrayKey>, T: IntoPhpVal> FromIterator<(K, T)> for PhpArray {
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let mut array = PhpArray::new();
+        array.extend(iter);
+        array
+    }
+}
+
+impl<K: Into<ArrayKey>, T: IntoPhpVal> Extend<(K, T)> for PhpArray {
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<T: IntoPhpVal> From<Vec<T>> for PhpArray {
+    fn from(input: Vec<T>) -> Self {
+        let mut array = PhpArray::with_capacity(input.len());
+        for value in input {
+            array.push(value);
+        }
+        array
+    }
+}
+
+impl<K: Into<ArrayKey>, T: IntoPhpVal> From<Vec<(K, T)>> for PhpArray {
+    fn from(input: Vec<(K, T)>) -> Self {
+        input.into_iter().collect()
+    }
+}
+
+impl<K: Into<ArrayKey>, T: IntoPhpVal> From<IndexMap<K, T>> for PhpArray {
+    fn from(input: IndexMap<K, T>) -> Self {
+        input.into_iter().collect()
+    }
+}
+
+impl From<PhpArray> for Vec<(ArrayKey, PhpVal)> {
+    fn from(input: PhpArray) -> Self {
+        input.items.into_iter().collect()
+    }
+}
+
+/// The values of the array in order, discarding the keys
+impl From<PhpArray> for Vec<PhpVal> {
+    fn from(input: PhpArray) -> Self {
+        input.items.into_iter().map(|(_, value)| value).collect()
+    }
+}
+
+impl From<PhpArray> for IndexMap<ArrayKey, PhpVal> {
+    fn from(input: PhpArray) -> Self {
+        input.items
+    }
+}
+
+impl From<PhpArray> for PhpVal {
+    fn from(input: PhpArray) -> Self {
+        PhpVal::Array(input.into())
+    }
+}
+
+impl FromPhpVal for PhpArray {
+    const TYPE_HINT: ZValType = ZValType::Array;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val.unwrap_reference() {
+            PhpVal::Array(items) => Ok(items.into()),
+            val => Err(CastError::new(val.get_type())),
+        }
+    }
+}
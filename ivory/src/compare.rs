@@ -0,0 +1,204 @@
+//! Php's loose and strict comparison rules
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+
+use crate::juggle::{self, Numeric};
+use crate::{ArrayKey, PhpVal};
+
+/// The php version whose comparison rules are followed
+///
+/// Php 8 changed how numbers are compared to non-numeric strings,
+/// php 7 converts the string to a number while php 8 compares both as strings.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompareMode {
+    Php7,
+    Php8,
+}
+
+impl Default for CompareMode {
+    fn default() -> Self {
+        CompareMode::Php8
+    }
+}
+
+fn threeway(a: f64, b: f64) -> Ordering {
+    // like php, uncomparable values (NAN) compare as greater
+    a.partial_cmp(&b).unwrap_or(Ordering::Greater)
+}
+
+fn compare_numbers(a: Numeric, b: Numeric) -> Ordering {
+    match (a, b) {
+        (Numeric::Long(a), Numeric::Long(b)) => a.cmp(&b),
+        (Numeric::Long(a), Numeric::Double(b)) => threeway(a as f64, b),
+        (Numeric::Double(a), Numeric::Long(b)) => threeway(a, b as f64),
+        (Numeric::Double(a), Numeric::Double(b)) => threeway(a, b),
+    }
+}
+
+/// Parse a numeric string, php 7 doesn't allow trailing whitespace
+fn numeric_string(value: &str, mode: CompareMode) -> Option<Numeric> {
+    match mode {
+        CompareMode::Php8 => juggle::parse_numeric(value),
+        CompareMode::Php7 => match juggle::parse_numeric_prefix(value) {
+            Some((numeric, "")) => Some(numeric),
+            _ => None,
+        },
+    }
+}
+
+/// Convert a value to a number the way php does before comparing, strings use their leading numeric part
+fn to_number(val: &PhpVal) -> Numeric {
+    match val {
+        PhpVal::Double(value) => Numeric::Double(*value),
+        PhpVal::String(value) => juggle::parse_numeric_prefix(value)
+            .map(|(numeric, _)| numeric)
+            .unwrap_or(Numeric::Long(0)),
+        val => Numeric::Long(val.to_long()),
+    }
+}
+
+/// Whether a numeric string is an integer too large for a php int
+fn is_overflowed(value: &str, numeric: Numeric) -> bool {
+    match numeric {
+        Numeric::Double(_) => !value.contains(['.', 'e', 'E']),
+        Numeric::Long(_) => false,
+    }
+}
+
+fn compare_strings(a: &str, b: &str, mode: CompareMode) -> Ordering {
+    if let (Some(numeric_a), Some(numeric_b)) = (numeric_string(a, mode), numeric_string(b, mode)) {
+        // integers overflowed into floats lose precision, so equal floats are compared as strings instead
+        let lost_precision = is_overflowed(a, numeric_a)
+            && is_overflowed(b, numeric_b)
+            && compare_numbers(numeric_a, numeric_b) == Ordering::Equal;
+        if !lost_precision {
+            return compare_numbers(numeric_a, numeric_b);
+        }
+    }
+    a.as_bytes().cmp(b.as_bytes())
+}
+
+fn compare_number_to_string(number: Numeric, string: &str, mode: CompareMode) -> Ordering {
+    match mode {
+        CompareMode::Php7 => compare_numbers(number, to_number(&PhpVal::String(string.to_string()))),
+        CompareMode::Php8 => match numeric_string(string, mode) {
+            Some(numeric) => compare_numbers(number, numeric),
+            None => {
+                let number = match number {
+                    Numeric::Long(value) => PhpVal::Long(value),
+                    Numeric::Double(value) => PhpVal::Double(value),
+                };
+                number.to_php_string().as_bytes().cmp(string.as_bytes())
+            }
+        },
+    }
+}
+
+/// Compare arrays by their size first, then the values of each key of `a`
+///
+/// Arrays with different keys are uncomparable and compare as greater.
+fn compare_arrays(a: &[(ArrayKey, PhpVal)], b: &[(ArrayKey, PhpVal)], mode: CompareMode) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len());
+    }
+    let b: HashMap<&ArrayKey, &PhpVal> = b.iter().map(|(key, value)| (key, value)).collect();
+    for (key, value) in a {
+        let ordering = match b.get(key) {
+            Some(other) => compare(value, other, mode),
+            None => Ordering::Greater,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn compare_objects(a: &IndexMap<String, PhpVal>, b: &IndexMap<String, PhpVal>, mode: CompareMode) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len());
+    }
+    for (key, value) in a {
+        let ordering = match b.get(key) {
+            Some(other) => compare(value, other, mode),
+            None => Ordering::Greater,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn is_null(val: &PhpVal) -> bool {
+    matches!(val, PhpVal::Undef | PhpVal::Null)
+}
+
+fn is_null_or_bool(val: &PhpVal) -> bool {
+    matches!(val, PhpVal::Undef | PhpVal::Null | PhpVal::Bool(_))
+}
+
+/// Compare two values like php's `<=>` operator
+pub(crate) fn compare(a: &PhpVal, b: &PhpVal, mode: CompareMode) -> Ordering {
+    match (a, b) {
+        (PhpVal::Reference(a), b) => compare(a, b, mode),
+        (a, PhpVal::Reference(b)) => compare(a, b, mode),
+        (PhpVal::Long(_), PhpVal::Long(_))
+        | (PhpVal::Long(_), PhpVal::Double(_))
+        | (PhpVal::Double(_), PhpVal::Long(_))
+        | (PhpVal::Double(_), PhpVal::Double(_)) => compare_numbers(to_number(a), to_number(b)),
+        (PhpVal::String(a), PhpVal::String(b)) => compare_strings(a, b, mode),
+        (PhpVal::Array(a), PhpVal::Array(b)) => compare_arrays(a, b, mode),
+        (PhpVal::Object(a), PhpVal::Object(b)) => compare_objects(a, b, mode),
+        // enum cases only equal themselves and are uncomparable to anything else
+        (PhpVal::EnumCase { .. }, PhpVal::EnumCase { .. }) if a == b => Ordering::Equal,
+        (a, PhpVal::String(b)) if is_null(a) => "".cmp(b.as_str()),
+        (PhpVal::String(a), b) if is_null(b) => a.as_str().cmp(""),
+        (PhpVal::Long(_), PhpVal::String(string)) | (PhpVal::Double(_), PhpVal::String(string)) => {
+            compare_number_to_string(to_number(a), string, mode)
+        }
+        (PhpVal::String(string), PhpVal::Long(_)) | (PhpVal::String(string), PhpVal::Double(_)) => {
+            compare_number_to_string(to_number(b), string, mode).reverse()
+        }
+        // null and bools are compared to the truthiness of the other value
+        (a, b) if is_null_or_bool(a) || is_null_or_bool(b) => a.to_bool().cmp(&b.to_bool()),
+        (PhpVal::EnumCase { .. }, _) | (_, PhpVal::EnumCase { .. }) => Ordering::Greater,
+        (PhpVal::Array(_), _) | (PhpVal::Object(_), _) => Ordering::Greater,
+        (_, PhpVal::Array(_)) | (_, PhpVal::Object(_)) => Ordering::Less,
+        // the remaining pairs contain a resource, which is compared by its handle
+        (a, b) => compare_numbers(to_number(a), to_number(b)),
+    }
+}
+
+/// Compare two values like php's `===` operator
+///
+/// Since `PhpVal` doesn't keep the identity of objects, objects are identical when their properties are.
+pub(crate) fn identical(a: &PhpVal, b: &PhpVal) -> bool {
+    match (a, b) {
+        (PhpVal::Reference(a), b) => identical(a, b),
+        (a, PhpVal::Reference(b)) => identical(a, b),
+        (a, b) if is_null(a) && is_null(b) => true,
+        (PhpVal::Bool(a), PhpVal::Bool(b)) => a == b,
+        (PhpVal::Long(a), PhpVal::Long(b)) => a == b,
+        #[allow(clippy::float_cmp)]
+        (PhpVal::Double(a), PhpVal::Double(b)) => a == b,
+        (PhpVal::String(a), PhpVal::String(b)) => a == b,
+        (PhpVal::Array(a), PhpVal::Array(b)) => {
+            // unlike `==`, the keys need to be in the same order
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((key_a, a), (key_b, b))| key_a == key_b && identical(a, b))
+        }
+        (PhpVal::Object(a), PhpVal::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, a)| matches!(b.get(key), Some(b) if identical(a, b)))
+        }
+        (PhpVal::Resource(a), PhpVal::Resource(b)) => a == b,
+        // enum cases are singletons
+        (PhpVal::EnumCase { .. }, PhpVal::EnumCase { .. }) => a == b,
+        _ => false,
+    }
+}
@@ -1,4 +1,5 @@
 use crate::zend::ZValType;
+use crate::{ArrayKey, PhpVal};
 use std::error::Error;
 use std::fmt;
 use std::fmt::Display;
@@ -6,8 +7,106 @@ use std::fmt::Display;
 #[derive(Debug)]
 pub struct CastError {
     pub actual: ZValType,
+    /// The array keys leading to the value that failed to cast, outermost first
+    pub path: Vec<ArrayKey>,
+    /// The accepted values, for types that only accept specific values
+    pub allowed: Vec<PhpVal>,
+    /// The accepted types, for types that accept multiple types
+    pub expected: Vec<ZValType>,
+    /// The accepted class, for types that only accept instances of a class
+    pub expected_class: Option<&'static str>,
+    /// Why a value of the right type was rejected
+    pub reason: Option<String>,
 }
 
+impl CastError {
+    pub fn new(actual: ZValType) -> Self {
+        CastError {
+            actual,
+            path: Vec::new(),
+            allowed: Vec::new(),
+            expected: Vec::new(),
+            expected_class: None,
+            reason: None,
+        }
+    }
+
+    /// A value of the right type that isn't one of the accepted values
+    pub fn invalid_value(actual: ZValType, allowed: Vec<PhpVal>) -> Self {
+        CastError {
+            actual,
+            path: Vec::new(),
+            allowed,
+            expected: Vec::new(),
+            expected_class: None,
+            reason: None,
+        }
+    }
+
+    /// A value that doesn't match any of the accepted types
+    pub fn unexpected_type(actual: ZValType, expected: Vec<ZValType>) -> Self {
+        CastError {
+            actual,
+            path: Vec::new(),
+            allowed: Vec::new(),
+            expected,
+            expected_class: None,
+            reason: None,
+        }
+    }
+
+    /// A value that isn't an instance of the class
+    pub fn unexpected_class(actual: ZValType, class: &'static str) -> Self {
+        CastError {
+            actual,
+            path: Vec::new(),
+            allowed: Vec::new(),
+            expected: Vec::new(),
+            expected_class: Some(class),
+            reason: None,
+        }
+    }
+
+    /// A value of the right type that was rejected for the given reason
+    pub fn invalid<S: Into<String>>(actual: ZValType, reason: S) -> Self {
+        CastError {
+            actual,
+            path: Vec::new(),
+            allowed: Vec::new(),
+            expected: Vec::new(),
+            expected_class: None,
+            reason: Some(reason.into()),
+        }
+    }
+
+    /// Mark the error as occurring inside the array element with the given key
+    pub fn with_key(mut self, key: ArrayKey) -> Self {
+        self.path.insert(0, key);
+        self
+    }
+
+    fn fmt_path(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, " at ")?;
+        }
+        for key in &self.path {
+            match key {
+                ArrayKey::Int(key) => write!(f, "[{}]", key)?,
+                ArrayKey::String(key) => write!(f, "[\"{}\"]", key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A zval type code that is not known to ivory
+#[derive(Debug)]
+pub struct UnknownTypeError(pub u8);
+
+/// A `Resource<T>` was created for a type that isn't registered as resource type
+#[derive(Debug)]
+pub struct UnregisteredResourceError(pub &'static str);
+
 #[derive(Debug)]
 pub enum ArgError {
     CastError(CastError),
@@ -20,9 +119,79 @@ impl From<CastError> for ArgError {
     }
 }
 
+/// The name php uses for a type in error messages
+pub(crate) fn php_type_name(ty: ZValType) -> String {
+    match ty {
+        ZValType::Long => "int".to_string(),
+        ZValType::Double => "float".to_string(),
+        ZValType::True | ZValType::False | ZValType::Bool => "bool".to_string(),
+        ty => ty.to_string(),
+    }
+}
+
 impl Display for CastError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Incorrect variable type, got {}", self.actual)
+        if !self.expected.is_empty() {
+            let expected: Vec<String> = self.expected.iter().cloned().map(php_type_name).collect();
+            write!(f, "Value")?;
+            self.fmt_path(f)?;
+            return write!(
+                f,
+                " must be of type {}, {} given",
+                expected.join("|"),
+                php_type_name(self.actual)
+            );
+        }
+
+        if let Some(class) = &self.expected_class {
+            write!(f, "Value")?;
+            self.fmt_path(f)?;
+            return write!(f, " must be of type {}, {} given", class, php_type_name(self.actual));
+        }
+
+        if let Some(reason) = &self.reason {
+            write!(f, "Invalid value")?;
+            self.fmt_path(f)?;
+            return write!(f, ", {}", reason);
+        }
+
+        let missing = self.actual == ZValType::Undef && !self.path.is_empty();
+        if !self.allowed.is_empty() {
+            write!(f, "Invalid value")?;
+        } else if missing {
+            write!(f, "Missing value")?;
+        } else {
+            write!(f, "Incorrect variable type")?;
+        }
+        self.fmt_path(f)?;
+        if !self.allowed.is_empty() {
+            write!(f, ", expected one of ")?;
+            for (i, value) in self.allowed.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                match value {
+                    PhpVal::String(value) => write!(f, "\"{}\"", value)?,
+                    PhpVal::Long(value) => write!(f, "{}", value)?,
+                    value => write!(f, "{:?}", value)?,
+                }
+            }
+        } else if !missing {
+            write!(f, ", got {}", self.actual)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for UnknownTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown zval type {}", self.0)
+    }
+}
+
+impl Display for UnregisteredResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Resource type {} is not registered", self.0)
     }
 }
 
@@ -41,6 +210,10 @@ impl Error for CastError {
     }
 }
 
+impl Error for UnknownTypeError {}
+
+impl Error for UnregisteredResourceError {}
+
 impl Error for ArgError {
     fn cause(&self) -> Option<&Error> {
         match self {
@@ -1,15 +1,18 @@
 use std::ffi::CString;
 use std::intrinsics::transmute;
 
-use ivory_sys::{php_printf, zend_error};
+use ivory_sys::{zend_error, zend_throw_error, zend_type_error};
+#[cfg(php8)]
+use ivory_sys::zend_value_error;
 
+use crate::output;
+
+/// Write the string to php's output
 pub fn printf<T: Into<Vec<u8>>>(string: T) {
-    let cstr = CString::new(string).unwrap();
-    unsafe {
-        php_printf(cstr.as_ptr());
-    }
+    output().write_bytes(&string.into());
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(i32)]
 pub enum ErrorLevel {
     Error = 1,
@@ -35,9 +38,52 @@ impl From<ErrorLevel> for i32 {
     }
 }
 
+/// Raise a php error with the message
+///
+/// Fatal levels like `Error` abort the request by jumping out of the current function,
+/// destructors of values on the rust stack are not run.
 pub fn error<T: Into<Vec<u8>>>(level: ErrorLevel, message: T) {
-    let cstr = CString::new(message).unwrap();
+    let cstr = message_cstring(message);
     unsafe {
-        zend_error(level.into(), cstr.as_ptr());
+        zend_error(level.into(), c_str!("%s"), cstr.as_ptr());
     }
 }
+
+/// Throw a `TypeError` exception
+///
+/// The exception is thrown once the exported function returns.
+pub fn throw_type_error<T: Into<Vec<u8>>>(message: T) {
+    let cstr = message_cstring(message);
+    unsafe {
+        zend_type_error(c_str!("%s"), cstr.as_ptr());
+    }
+}
+
+/// Throw an `Error` exception
+///
+/// The exception is thrown once the exported function returns.
+pub fn throw_error<T: Into<Vec<u8>>>(message: T) {
+    let cstr = message_cstring(message);
+    unsafe {
+        zend_throw_error(std::ptr::null_mut(), c_str!("%s"), cstr.as_ptr());
+    }
+}
+
+/// Throw a `ValueError` exception, only available on php 8
+///
+/// The exception is thrown once the exported function returns.
+#[cfg(php8)]
+pub fn throw_value_error<T: Into<Vec<u8>>>(message: T) {
+    let cstr = message_cstring(message);
+    unsafe {
+        zend_value_error(c_str!("%s"), cstr.as_ptr());
+    }
+}
+
+/// Convert a message to a C string, dropping NUL bytes which would otherwise cut the message short
+fn message_cstring<T: Into<Vec<u8>>>(message: T) -> CString {
+    let mut message = message.into();
+    message.retain(|&byte| byte != 0);
+    // the NUL bytes were removed above
+    unsafe { CString::from_vec_unchecked(message) }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::zend::ZValType;
+use crate::{ArrayKey, CastError, FromPhpVal, PhpVal};
+
+/// The named fields of a php associative array or object
+///
+/// Used by `#[derive(FromPhp)]` and useful for implementing `FromPhpVal` for record-like types by hand,
+/// errors returned when taking a field contain the key of the field.
+pub struct ArrayFields {
+    fields: HashMap<ArrayKey, PhpVal>,
+    coerce: bool,
+}
+
+impl ArrayFields {
+    pub fn new(val: PhpVal) -> Result<Self, CastError> {
+        match val.unwrap_reference() {
+            PhpVal::Array(items) => Ok(ArrayFields {
+                fields: items.into_iter().collect(),
+                coerce: false,
+            }),
+            PhpVal::Object(properties) => Ok(ArrayFields {
+                fields: properties
+                    .into_iter()
+                    .map(|(key, value)| (ArrayKey::from(key), value))
+                    .collect(),
+                coerce: false,
+            }),
+            val => Err(CastError::new(val.get_type())),
+        }
+    }
+
+    /// Convert the fields using php's coercive typing rules, see `FromPhpVal::coerce_php_val`
+    pub fn coercing(mut self) -> Self {
+        self.coerce = true;
+        self
+    }
+
+    /// Take the value of a field, a missing field is converted from `Undef`
+    pub fn take<T: FromPhpVal>(&mut self, key: &str) -> Result<T, CastError> {
+        let key = ArrayKey::from(key);
+        let value = self.fields.remove(&key).unwrap_or(PhpVal::Undef);
+        let result = match value {
+            PhpVal::Undef => T::from_php_val(value),
+            value if self.coerce => T::coerce_php_val(value),
+            value => T::from_php_val(value),
+        };
+        result.map_err(|err| err.with_key(key))
+    }
+
+    /// Take the value of a field, using the default value if the field is missing
+    pub fn take_or_default<T: FromPhpVal + Default>(&mut self, key: &str) -> Result<T, CastError> {
+        if self.contains(key) {
+            self.take(key)
+        } else {
+            Ok(T::default())
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        match self.fields.get(&ArrayKey::from(key)) {
+            Some(value) => value.get_type() != ZValType::Undef,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Php's type juggling rules for scalar values
+
+use crate::zend::ZValType;
+use crate::{CastError, PhpVal};
+
+/// The value of a numeric string
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Numeric {
+    Long(i64),
+    Double(f64),
+}
+
+/// Parse a string if it's numeric by php's rules, surrounding whitespace is allowed
+pub(crate) fn parse_numeric(input: &str) -> Option<Numeric> {
+    let trimmed = input.trim_matches(|c| " \t\n\r\x0b\x0c".contains(c));
+    let (numeric, rest) = parse_numeric_prefix(trimmed)?;
+    if rest.is_empty() {
+        Some(numeric)
+    } else {
+        None
+    }
+}
+
+/// Parse the leading numeric part of a string, returning the value and the remaining string
+pub(crate) fn parse_numeric_prefix(input: &str) -> Option<(Numeric, &str)> {
+    let input = input.trim_start_matches(|c| " \t\n\r\x0b\x0c".contains(c));
+    let bytes = input.as_bytes();
+    let mut end = 0;
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    let int_start = end;
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    let mut digits = end - int_start;
+    let mut is_double = false;
+    if end < bytes.len() && bytes[end] == b'.' {
+        let fraction_start = end + 1;
+        let mut fraction_end = fraction_start;
+        while fraction_end < bytes.len() && bytes[fraction_end].is_ascii_digit() {
+            fraction_end += 1;
+        }
+        if digits > 0 || fraction_end > fraction_start {
+            digits += fraction_end - fraction_start;
+            end = fraction_end;
+            is_double = true;
+        }
+    }
+    if digits == 0 {
+        return None;
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent_end = end + 1;
+        if exponent_end < bytes.len() && (bytes[exponent_end] == b'+' || bytes[exponent_end] == b'-') {
+            exponent_end += 1;
+        }
+        let exponent_digits_start = exponent_end;
+        while exponent_end < bytes.len() && bytes[exponent_end].is_ascii_digit() {
+            exponent_end += 1;
+        }
+        if exponent_end > exponent_digits_start {
+            end = exponent_end;
+            is_double = true;
+        }
+    }
+
+    let (number, rest) = input.split_at(end);
+    let numeric = if is_double {
+        Numeric::Double(number.parse().ok()?)
+    } else {
+        match number.parse() {
+            Ok(value) => Numeric::Long(value),
+            // integers that don't fit in a php int become a float
+            Err(_) => Numeric::Double(number.parse().ok()?),
+        }
+    };
+    Some((numeric, rest))
+}
+
+/// The default value of php's `precision` ini setting
+pub(crate) const DEFAULT_PRECISION: usize = 14;
+
+/// Format a float the way php converts it to a string, using `precision` significant digits
+pub(crate) fn double_to_string(value: f64, precision: usize) -> String {
+    let precision = precision.max(1);
+
+    if value.is_nan() {
+        return "NAN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+    if value == 0.0 {
+        return if value.is_sign_negative() { "-0" } else { "0" }.to_string();
+    }
+
+    let formatted = format!("{:.*e}", precision - 1, value);
+    let (mantissa, exponent) = formatted.split_at(formatted.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let digits = digits.trim_end_matches('0');
+
+    if !(-4..precision as i32).contains(&exponent) {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        let exponent_sign = if exponent < 0 { "-" } else { "+" };
+        format!("{}{}.{}E{}{}", sign, first, rest, exponent_sign, exponent.abs())
+    } else if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        format!("{}0.{}{}", sign, zeros, digits)
+    } else {
+        let int_len = exponent as usize + 1;
+        if digits.len() <= int_len {
+            format!("{}{}{}", sign, digits, "0".repeat(int_len - digits.len()))
+        } else {
+            let (int_part, fraction) = digits.split_at(int_len);
+            format!("{}{}.{}", sign, int_part, fraction)
+        }
+    }
+}
+
+/// Convert a float to an int if it can be represented without losing precision
+///
+/// Php 7 silently discards the fractional part, which is deprecated since php 8.1 so we reject it instead.
+fn double_to_long(value: f64) -> Result<i64, CastError> {
+    // i64::MAX as f64 rounds up to 2^63 which is out of range
+    if !(-9.223_372_036_854_776e18..9.223_372_036_854_776e18).contains(&value) {
+        Err(CastError::invalid(
+            ZValType::Double,
+            format!("{} is out of range for an int", double_to_string(value, DEFAULT_PRECISION)),
+        ))
+    } else if value.fract() != 0.0 {
+        Err(CastError::invalid(
+            ZValType::Double,
+            format!("{} can't be converted to an int without losing precision", double_to_string(value, DEFAULT_PRECISION)),
+        ))
+    } else {
+        Ok(value as i64)
+    }
+}
+
+/// Convert a float to an int like php's `(int)` cast, out of range values wrap around
+pub(crate) fn double_to_long_wrapping(value: f64) -> i64 {
+    const TWO_POW_64: f64 = 18_446_744_073_709_551_616.0;
+
+    if !value.is_finite() {
+        return 0;
+    }
+    if (-9.223_372_036_854_776e18..9.223_372_036_854_776e18).contains(&value) {
+        return value as i64;
+    }
+    let mut modulo = value.trunc() % TWO_POW_64;
+    if modulo < 0.0 {
+        modulo += TWO_POW_64;
+    }
+    // the remainder is in 0..2^64 and reinterpreted as a signed int
+    modulo as u64 as i64
+}
+
+/// Convert a float parsed from a string to an int, out of range values saturate
+fn double_to_long_saturating(value: f64) -> i64 {
+    if value.is_nan() {
+        0
+    } else {
+        value as i64
+    }
+}
+
+/// Convert a value to an int like php's `intval`
+pub(crate) fn to_long(val: &PhpVal) -> i64 {
+    match val {
+        PhpVal::Undef | PhpVal::Null => 0,
+        PhpVal::Bool(value) => *value as i64,
+        PhpVal::Long(value) => *value,
+        PhpVal::Double(value) => double_to_long_wrapping(*value),
+        PhpVal::String(value) => match parse_numeric_prefix(value) {
+            Some((Numeric::Long(value), _)) => value,
+            Some((Numeric::Double(value), _)) if value.is_infinite() => 0,
+            Some((Numeric::Double(value), _)) => double_to_long_saturating(value),
+            None => 0,
+        },
+        PhpVal::Array(items) => !items.is_empty() as i64,
+        PhpVal::Object(_) | PhpVal::EnumCase { .. } => 1,
+        PhpVal::Resource(handle) => *handle as i64,
+        PhpVal::Reference(inner) => to_long(inner),
+    }
+}
+
+/// Convert a value to a float like php's `floatval`
+pub(crate) fn to_double(val: &PhpVal) -> f64 {
+    match val {
+        PhpVal::Double(value) => *value,
+        PhpVal::String(value) => match parse_numeric_prefix(value) {
+            Some((Numeric::Long(value), _)) => value as f64,
+            Some((Numeric::Double(value), _)) => value,
+            None => 0.0,
+        },
+        PhpVal::Reference(inner) => to_double(inner),
+        val => to_long(val) as f64,
+    }
+}
+
+/// Convert a value to a bool following php's truthiness rules
+pub(crate) fn to_bool(val: &PhpVal) -> bool {
+    match val {
+        PhpVal::Undef | PhpVal::Null => false,
+        PhpVal::Bool(value) => *value,
+        PhpVal::Long(value) => *value != 0,
+        PhpVal::Double(value) => *value != 0.0,
+        PhpVal::String(value) => !(value.is_empty() || value == "0"),
+        PhpVal::Array(items) => !items.is_empty(),
+        PhpVal::Object(_) | PhpVal::EnumCase { .. } | PhpVal::Resource(_) => true,
+        PhpVal::Reference(inner) => to_bool(inner),
+    }
+}
+
+/// Convert a value to a string like php's `strval`, floats are formatted with `precision` significant digits
+pub(crate) fn to_php_string(val: &PhpVal, precision: usize) -> String {
+    match val {
+        PhpVal::Undef | PhpVal::Null | PhpVal::Bool(false) => String::new(),
+        PhpVal::Bool(true) => "1".to_string(),
+        PhpVal::Long(value) => value.to_string(),
+        PhpVal::Double(value) => double_to_string(*value, precision),
+        PhpVal::String(value) => value.clone(),
+        PhpVal::Array(_) => "Array".to_string(),
+        PhpVal::Object(_) | PhpVal::EnumCase { .. } => "Object".to_string(),
+        PhpVal::Resource(handle) => format!("Resource id #{}", handle),
+        PhpVal::Reference(inner) => to_php_string(inner, precision),
+    }
+}
+
+/// Coerce a value for an int argument in non-strict mode
+pub(crate) fn coerce_long(val: PhpVal) -> Result<i64, CastError> {
+    match val.unwrap_reference() {
+        PhpVal::Long(value) => Ok(value),
+        PhpVal::Double(value) => double_to_long(value),
+        PhpVal::Bool(value) => Ok(value as i64),
+        PhpVal::String(value) => match parse_numeric(&value) {
+            Some(Numeric::Long(value)) => Ok(value),
+            Some(Numeric::Double(value)) => double_to_long(value),
+            None => Err(CastError::new(ZValType::String)),
+        },
+        val => Err(CastError::new(val.get_type())),
+    }
+}
+
+/// Coerce a value for a float argument in non-strict mode
+pub(crate) fn coerce_double(val: PhpVal) -> Result<f64, CastError> {
+    match val.unwrap_reference() {
+        PhpVal::Double(value) => Ok(value),
+        PhpVal::Long(value) => Ok(value as f64),
+        PhpVal::Bool(value) => Ok(if value { 1.0 } else { 0.0 }),
+        PhpVal::String(value) => match parse_numeric(&value) {
+            Some(Numeric::Long(value)) => Ok(value as f64),
+            Some(Numeric::Double(value)) => Ok(value),
+            None => Err(CastError::new(ZValType::String)),
+        },
+        val => Err(CastError::new(val.get_type())),
+    }
+}
+
+/// Coerce a value for a string argument in non-strict mode
+pub(crate) fn coerce_string(val: PhpVal) -> Result<String, CastError> {
+    match val.unwrap_reference() {
+        PhpVal::String(value) => Ok(value),
+        val @ PhpVal::Long(_) | val @ PhpVal::Double(_) | val @ PhpVal::Bool(_) => {
+            Ok(to_php_string(&val, DEFAULT_PRECISION))
+        }
+        val => Err(CastError::new(val.get_type())),
+    }
+}
+
+/// Coerce a value for a bool argument in non-strict mode
+pub(crate) fn coerce_bool(val: PhpVal) -> Result<bool, CastError> {
+    match val.unwrap_reference() {
+        val @ PhpVal::Bool(_) | val @ PhpVal::Long(_) | val @ PhpVal::Double(_) | val @ PhpVal::String(_) => {
+            Ok(to_bool(&val))
+        }
+        val => Err(CastError::new(val.get_type())),
+    }
+}
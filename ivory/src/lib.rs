@@ -11,18 +11,169 @@
 //! - rust `bool` to/from php `bool`
 //! - rust `String` to/from php `string`
 //! - rust `Vec<T>` to/from php `array`
-//! - rust `Vec<(u64, T)>` to/from php `array`
+//! - rust `Vec<(K, T)>` to php `array`
+//! - rust `HashMap<K, T>`, `BTreeMap<K, T>` and `IndexMap<K, T>` to/from php `array`
+//! - [`PhpArray`](struct.PhpArray.html) to/from php `array`, keeping the order and keys of the array
 //!
-//! Where `T` is a type that can be converted from/to php
+//! Where `T` is a type that can be converted from/to php and `K` is a `String` or integer type.
+//! Like php, string keys containing a canonical integer such as `"5"` are converted to integer keys.
+//!
+//! Integer arguments are range checked, a value that doesn't fit in the rust type results in an error.
+//! Returned `u64`, `usize` and `isize` values that don't fit in a php `int` are converted to a `float`,
+//! the same as php does for integer overflows, return a `String` instead if the exact value is needed.
+//!
+//! Arguments that can't be converted throw a `TypeError`.
+//!
+//! # Coercive typing
+//!
+//! Like php's own functions, arguments are coerced unless the calling file declared `strict_types=1`:
+//! numeric strings are accepted for `int` and `float` arguments, ints for `float` arguments,
+//! scalars and objects implementing `__toString` for `string` arguments and any scalar for `bool` arguments.
+//! With `strict_types=1` only values of the exact type are accepted, except for ints which are still accepted for `float` arguments.
+//! The elements of arrays, struct fields and union types are coerced the same way,
+//! see [`FromPhpVal::coerce_php_val`](trait.FromPhpVal.html#method.coerce_php_val).
+//!
+//! The coercion is stricter than php 7.3's own functions and follows the rules php 8 moved towards instead:
+//! `null` is never coerced, floats with a fractional part are not accepted for `int` arguments
+//! and strings with trailing non-numeric characters like `"5 apples"` are rejected for `int` and `float` arguments.
+//! Php 7.3 would convert `null` to `0`, `""` or `false`, truncate the float and use the leading number with a notice.
+//! Use `Option<T>` to accept `null`.
+//!
+//! For explicit conversions `PhpVal` has `to_bool`, `to_long`, `to_double`, `to_php_string` and `is_numeric`,
+//! which follow php's casting rules.
+//! `loose_eq`, `strict_eq` and `spaceship` compare values like php's `==`, `===` and `<=>` operators,
+//! following php 8's rules by default or php 7's with a [`CompareMode`](enum.CompareMode.html).
+//!
+//! When converting arrays from php, the keys are ignored for `Vec<T>` and the values are taken in array order,
+//! cast errors contain the keys of the element that failed to cast.
+//!
+//! To read large arrays without converting every element, take the argument as a
+//! [`ZArray`](zend/struct.ZArray.html), which borrows the array from php.
+//! Lookups use php's hash table and elements are converted on demand with `ZVal::cast`.
+//!
+//! # Custom types
+//!
+//! Argument types are converted using the [`FromPhpVal`](trait.FromPhpVal.html) trait and return types
+//! using the [`IntoPhpVal`](trait.IntoPhpVal.html) trait, implement these for your own types to use them in exported functions.
+//! The `TYPE_HINT` of `FromPhpVal` determines the type php shows for the argument.
+//!
+//! # Structs
+//!
+//! Structs with named fields can be converted from and to php associative arrays with `#[derive(FromPhp, IntoPhp)]`.
+//!
+//! - `#[php(rename = "key")]` uses a different array key for a field
+//! - `#[php(default)]` uses the `Default` value of the field when the key is missing
+//! - `#[php(skip)]` ignores the field, it is always set to its `Default` value
+//!
+//! Fields of type `Option<T>` are optional, missing keys are converted to `None`.
+//! When converting from php, objects are accepted as well as arrays, reading the public properties of the object.
+//! With `#[php(object)]` on the struct, it is converted into a `stdClass` object instead of an array.
+//!
+//! # Enums
+//!
+//! Fieldless enums can be converted from and to php strings or ints with `#[derive(FromPhp, IntoPhp)]`,
+//! the value of each variant is set with `#[php(value = "name")]` or `#[php(value = 1)]` and defaults to the variant name.
+//! Values that don't match any variant result in an error listing the allowed values.
+//!
+//! With `#[php(constants)]` on the enum, a constant is registered for every variant, named `ENUM_VARIANT`,
+//! a different prefix can be set with `#[php(constants = "PREFIX_")]`.
+//! The enum needs to be in scope of the `ivory_module!` call.
+//!
+//! # Native enums
+//!
+//! When building against php 8.1 or later, fieldless enums marked with `#[ivory_enum]` are registered as native
+//! php enums at module startup, the enum needs to be in scope of the `ivory_module!` call.
+//! Cases are pure unless they set a backing value with `#[php(value = "H")]` or `#[php(value = 1)]`,
+//! in which case every case needs a unique value of the same type. Older php versions fail to compile.
+//!
+//! Arguments of the enum type are declared with the enum class as type, other values throw a `TypeError`
+//! and php's `Enum::from` throws a `ValueError` for unknown backing values.
+//! Registered enums are included in the stubs and cases are passed around as `PhpVal::EnumCase`.
+//!
+//! # Union types
+//!
+//! Enums where every variant has a single unnamed field, like `enum IdOrName { Id(i64), Name(String) }`,
+//! accept any of the types of their variants, the variants are tried in order.
+//! When coercing, a variant matching the type of the value exactly is preferred.
+//! Php 8 builds declare the union type for these arguments, since php 7 can't declare union types
+//! the arguments have no type hint there. The generated [stubs](#stubs) always include the union type.
+//!
+//! # Optional arguments
+//!
+//! Arguments of type `Option<T>` are optional and receive `None` if omitted,
+//! other arguments can be made optional by providing a default value with `#[default = value]`.
+//!
+//! Since php 7 has no way to declare default values for internal functions, the default values are not
+//! visible through reflection, the arguments are marked as optional.
+//! The default values are included in the generated [stubs](#stubs) instead.
+//!
+//! `Option<T>` arguments treat an omitted argument and `null` the same, to tell them apart use
+//!
+//! - `Optional<T>` for arguments that can be omitted but don't accept `null`
+//! - `Nullable<T>` for arguments that accept `null` but can't be omitted,
+//!   with a default value this is the equivalent of `?int $x = 5`
+//! - `Optional<Nullable<T>>` for arguments that can be omitted and accept `null`
+//!
+//! # By-reference arguments
+//!
+//! Arguments of type `&mut PhpRef<T>` are passed by reference,
+//! changes to the value are visible to the caller after the function returns.
+//! The current value is converted like any other argument, an undefined or `null` variable only has a value
+//! if `T` accepts `null`, otherwise it has to be `set` first.
+//!
+//! To modify an array in place without converting it, take it as a [`&mut ZHashTable`](zend/struct.ZHashTable.html)
+//! argument instead, shared arrays are copied before the first change like php does.
+//! A `ZHashTable` can also be built from scratch and returned.
+//!
+//! # Stubs
+//!
+//! `ivory_module!` generates a `php_stubs()` function returning a php file that declares all exported functions
+//! with their argument types and default values, for IDEs and static analysis tools.
+//! Defaults that can't be written in php are declared as `UNKNOWN`, like php's own stubs do.
+//!
+//! # Resources
+//!
+//! Exported functions can return a `Resource<T>` to hand a rust value over to php,
+//! and take it back as a `&Resource<T>` argument, the value is dropped once php frees the resource.
+//! `Resource::new` returns an error if the type isn't registered, return the `Result` to throw it as an `Error` exception.
+//!
+//! Resource types are registered when the module is loaded for every `Resource<T>` used in an exported function,
+//! the type `T` needs to be in scope of the `ivory_module!` call.
+//!
+//! # Logging
+//!
+//! With the `log` feature enabled, records from the `log` crate are routed to php's error handling,
+//! see the [logger](logger/index.html) module for details.
+//!
+//! # Variadic functions
+//!
+//! A trailing `Variadic<T>` argument collects all remaining arguments passed to the function,
+//! each converted to `T`.
 
 #[macro_use]
 pub mod macros;
+mod args;
+mod array;
+mod compare;
 pub mod error;
 
 pub mod externs;
+mod fields;
 pub mod info;
+mod juggle;
+#[cfg(feature = "log")]
+pub mod logger;
+mod output;
 mod phpval;
+pub mod stubs;
 pub mod zend;
-pub use crate::error::{ArgError, CastError};
-pub use crate::phpval::{ArrayKey, PhpVal};
-pub use ivory_macro::{ivory_export, ivory_module};
+pub use crate::args::{FromArg, Nullable, Optional, PhpRef, Variadic};
+pub use crate::array::PhpArray;
+pub use crate::compare::CompareMode;
+pub use crate::error::{ArgError, CastError, UnknownTypeError, UnregisteredResourceError};
+pub use crate::fields::ArrayFields;
+pub use crate::output::{output, Output};
+pub use crate::phpval::{ArrayKey, ArrayKeyRef, FromArrayKey, FromPhpVal, IntoPhpVal, PhpVal};
+pub use crate::zend::Resource;
+pub use indexmap::IndexMap;
+pub use ivory_macro::{ivory_enum, ivory_export, ivory_module, FromPhp, IntoPhp};
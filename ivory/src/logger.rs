@@ -0,0 +1,130 @@
+//! Backend for the `log` crate which routes log records to php's error handling
+//!
+//! The logger is installed automatically when the module is loaded if the `log` feature is enabled.
+//!
+//! Where each log level is routed to can be configured per level through ini settings
+//! named `<module>.log_<level>`, e.g. `demo.log_warn`, with the following values:
+//!
+//! - `warning`, `notice` or `deprecated`: raise a php error of the level
+//! - `log`: write the message to php's error log
+//! - `off`: ignore the message
+//!
+//! By default errors and warnings raise a php warning, info is written to the error log and debug and trace are ignored.
+//! Fatal php errors are not supported since they would abort the request from inside the logger,
+//! unknown values fall back to the default of the level.
+//!
+//! Since php isn't thread safe, log records should only be emitted from the thread handling the php request.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+use ivory_sys::php_log_err_with_severity;
+
+use crate::externs::{error, ErrorLevel};
+use crate::zend::ini::{ini_string, register_ini_entries};
+
+const LEVELS: [(Level, &str, &str); 5] = [
+    (Level::Error, "error", "warning"),
+    (Level::Warn, "warn", "warning"),
+    (Level::Info, "info", "log"),
+    (Level::Debug, "debug", "off"),
+    (Level::Trace, "trace", "off"),
+];
+
+#[derive(Debug, PartialEq)]
+enum LogTarget {
+    Error(ErrorLevel),
+    Log,
+    Off,
+}
+
+impl LogTarget {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warning" => Some(LogTarget::Error(ErrorLevel::Warning)),
+            "notice" => Some(LogTarget::Error(ErrorLevel::Notice)),
+            "deprecated" => Some(LogTarget::Error(ErrorLevel::Deprecated)),
+            "log" => Some(LogTarget::Log),
+            "off" | "" => Some(LogTarget::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Syslog severity for messages written to the error log
+fn syslog_severity(level: Level) -> c_int {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+pub struct PhpLogger {
+    module: String,
+}
+
+impl PhpLogger {
+    fn setting_name(&self, level: Level) -> Option<String> {
+        LEVELS
+            .iter()
+            .find(|(lvl, _, _)| *lvl == level)
+            .map(|(_, name, _)| format!("{}.log_{}", self.module, name))
+    }
+
+    fn target(&self, level: Level) -> LogTarget {
+        let configured = self
+            .setting_name(level)
+            .and_then(|name| ini_string(&name))
+            .and_then(|value| LogTarget::parse(&value));
+        let default = LEVELS
+            .iter()
+            .find(|(lvl, _, _)| *lvl == level)
+            .and_then(|(_, _, default)| LogTarget::parse(default));
+        configured.or(default).unwrap_or(LogTarget::Off)
+    }
+}
+
+impl Log for PhpLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.target(metadata.level()) != LogTarget::Off
+    }
+
+    fn log(&self, record: &Record) {
+        let message = format!("{}: {}", self.module, record.args()).replace('\0', "");
+        match self.target(record.level()) {
+            LogTarget::Error(level) => error(level, message),
+            LogTarget::Log => {
+                let message = CString::new(message).unwrap();
+                unsafe {
+                    php_log_err_with_severity(
+                        message.as_ptr() as *mut c_char,
+                        syslog_severity(record.level()),
+                    );
+                }
+            }
+            LogTarget::Off => {}
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub(crate) fn register(module_name: *const c_char, module_number: c_int) {
+    let module = unsafe { CStr::from_ptr(module_name) }
+        .to_string_lossy()
+        .into_owned();
+    let entries: Vec<(String, String)> = LEVELS
+        .iter()
+        .map(|(_, name, default)| (format!("{}.log_{}", module, name), default.to_string()))
+        .collect();
+    register_ini_entries(&entries, module_number);
+
+    let logger = Box::new(PhpLogger { module });
+    // if a logger is already installed we leave it in place
+    if log::set_logger(Box::leak(logger)).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
@@ -4,3 +4,40 @@ macro_rules! c_str {
         concat!($s, "\0").as_ptr() as *const ::std::os::raw::c_char
     }};
 }
+
+/// Fail to compile unless ivory is built against php 8.1 or later, used by features that need php 8.1
+#[cfg(php81)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_php81 {
+    ($feature:expr) => {};
+}
+
+/// Fail to compile unless ivory is built against php 8.1 or later, used by features that need php 8.1
+#[cfg(not(php81))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_php81 {
+    ($feature:expr) => {
+        compile_error!(concat!($feature, " needs php 8.1 or later"));
+    };
+}
+
+/// Print formatted text to php's output
+#[macro_export]
+macro_rules! php_print {
+    ($($arg:tt)*) => {{
+        let _ = ::std::fmt::Write::write_fmt(&mut $crate::output(), format_args!($($arg)*));
+    }};
+}
+
+/// Print formatted text followed by a newline to php's output
+#[macro_export]
+macro_rules! php_println {
+    () => {
+        $crate::php_print!("\n")
+    };
+    ($($arg:tt)*) => {{
+        $crate::php_print!("{}\n", format_args!($($arg)*));
+    }};
+}
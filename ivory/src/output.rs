@@ -0,0 +1,41 @@
+use std::fmt;
+use std::io;
+use std::os::raw::c_char;
+
+use ivory_sys::php_output_write;
+
+/// Handle to php's output, anything written to it goes through php's output layer
+/// and thus respects output buffering
+///
+/// Use `php_print!` and `php_println!` for formatted output
+pub struct Output {
+    _private: (),
+}
+
+pub fn output() -> Output {
+    Output { _private: () }
+}
+
+impl Output {
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> usize {
+        unsafe { php_output_write(bytes.as_ptr() as *const c_char, bytes.len()) }
+    }
+}
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.write_bytes(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // flushing is left to php's output layer
+        Ok(())
+    }
+}
+
+impl fmt::Write for Output {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
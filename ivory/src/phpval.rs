@@ -1,32 +1,91 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::hash::Hash;
 
+use indexmap::IndexMap;
+
+use crate::compare::{self, CompareMode};
+use crate::juggle;
 use crate::zend::ZValType;
 use crate::CastError;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+/// The key of a php array element
+///
+/// Like php, strings containing a canonical integer such as `"5"` or `"-1"` are converted to an integer key
+/// when creating a key with `From` or converting an array to a zval, `"05"` or `"+5"` stay string keys.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub enum ArrayKey {
     String(String),
-    Int(u64),
+    Int(i64),
+}
+
+impl ArrayKey {
+    /// Convert a string key containing a canonical integer into an integer key
+    pub fn normalize(self) -> Self {
+        match self {
+            ArrayKey::String(key) => match key.parse::<i64>() {
+                // only the canonical form is converted, not "05", "+5" or "-0"
+                Ok(int) if int.to_string() == key => ArrayKey::Int(int),
+                _ => ArrayKey::String(key),
+            },
+            key => key,
+        }
+    }
+}
+
+/// The key of an element borrowed from a php array, as yielded when iterating over a `ZArray`
+///
+/// Convert it into an `ArrayKey` to keep the key after the array is gone.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum ArrayKeyRef<'a> {
+    String(&'a str),
+    Int(i64),
+}
+
+impl From<ArrayKeyRef<'_>> for ArrayKey {
+    fn from(input: ArrayKeyRef<'_>) -> Self {
+        match input {
+            ArrayKeyRef::String(key) => ArrayKey::String(key.to_string()),
+            ArrayKeyRef::Int(index) => ArrayKey::Int(index),
+        }
+    }
 }
 
 macro_rules! impl_from_array_key {
-    ($type:ty, $variant:ident, $type2:ty) => {
+    ($type:ty) => {
         impl From<$type> for ArrayKey {
             fn from(input: $type) -> Self {
                 #[allow(clippy::cast_lossless)]
-                ArrayKey::$variant(input as $type2)
+                ArrayKey::Int(input as i64)
             }
         }
     };
 }
 
-impl_from_array_key!(String, String, String);
-impl_from_array_key!(u64, Int, u64);
-impl_from_array_key!(u32, Int, u64);
-impl_from_array_key!(u16, Int, u64);
-impl_from_array_key!(u8, Int, u64);
-impl_from_array_key!(usize, Int, u64);
+impl From<String> for ArrayKey {
+    fn from(input: String) -> Self {
+        ArrayKey::String(input).normalize()
+    }
+}
+
+impl From<&str> for ArrayKey {
+    fn from(input: &str) -> Self {
+        ArrayKey::from(input.to_string())
+    }
+}
+
+impl_from_array_key!(i64);
+impl_from_array_key!(i32);
+impl_from_array_key!(i16);
+impl_from_array_key!(i8);
+impl_from_array_key!(isize);
+impl_from_array_key!(u32);
+impl_from_array_key!(u16);
+impl_from_array_key!(u8);
+// like php's hash index, values above i64::MAX wrap around
+impl_from_array_key!(u64);
+impl_from_array_key!(usize);
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum PhpVal {
@@ -37,9 +96,13 @@ pub enum PhpVal {
     Double(f64),
     String(String),
     Array(Vec<(ArrayKey, PhpVal)>),
-    Object(HashMap<String, PhpVal>),
+    /// The public properties of an object in declaration order, converted into a `stdClass` object
+    Object(IndexMap<String, PhpVal>),
+    /// The handle of a resource, only resources that are still alive can be converted back into a zval
     Resource(u64),
-    Reference(),
+    /// A case of a php 8.1 enum, converted back into the case of the enum class with that name
+    EnumCase { class: String, case: String },
+    Reference(Box<PhpVal>),
 }
 
 impl PhpVal {
@@ -55,9 +118,91 @@ impl PhpVal {
             PhpVal::Array(_) => ZValType::Array,
             PhpVal::Object(_) => ZValType::Object,
             PhpVal::Resource(_) => ZValType::Resource,
-            PhpVal::Reference() => ZValType::Reference,
+            PhpVal::EnumCase { .. } => ZValType::Object,
+            PhpVal::Reference(_) => ZValType::Reference,
+        }
+    }
+
+    /// Get the referenced value if the value is a reference
+    pub fn unwrap_reference(self) -> PhpVal {
+        match self {
+            PhpVal::Reference(inner) => inner.unwrap_reference(),
+            val => val,
+        }
+    }
+
+    /// Whether the value is truthy, the same as `(bool)$val`
+    pub fn to_bool(&self) -> bool {
+        juggle::to_bool(self)
+    }
+
+    /// Convert the value to an int, the same as `intval($val)`
+    ///
+    /// Strings are converted from their leading numeric part, floats are truncated and wrap around when out of range.
+    pub fn to_long(&self) -> i64 {
+        juggle::to_long(self)
+    }
+
+    /// Convert the value to a float, the same as `floatval($val)`
+    pub fn to_double(&self) -> f64 {
+        juggle::to_double(self)
+    }
+
+    /// Convert the value to a string, the same as `strval($val)` with the default `precision` of 14
+    ///
+    /// Arrays are converted to `"Array"`, objects to `"Object"` since the value has no access to `__toString`.
+    pub fn to_php_string(&self) -> String {
+        juggle::to_php_string(self, juggle::DEFAULT_PRECISION)
+    }
+
+    /// Convert the value to a string, formatting floats with `precision` significant digits
+    pub fn to_php_string_with_precision(&self, precision: usize) -> String {
+        juggle::to_php_string(self, precision)
+    }
+
+    /// Whether the value is an int, float or numeric string, the same as `is_numeric($val)` in php 8
+    ///
+    /// Numeric strings can have leading and trailing whitespace but no other trailing characters.
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            PhpVal::Long(_) | PhpVal::Double(_) => true,
+            PhpVal::String(value) => juggle::parse_numeric(value).is_some(),
+            PhpVal::Reference(inner) => inner.is_numeric(),
+            _ => false,
         }
     }
+
+    /// Compare two values like php 8's `==` operator
+    ///
+    /// Unlike `PartialEq`, this follows php's juggling rules, `"1e3"` is equal to `"1000"` and
+    /// arrays are equal when they have the same key and value pairs in any order.
+    pub fn loose_eq(&self, other: &PhpVal) -> bool {
+        self.loose_eq_with_mode(other, CompareMode::Php8)
+    }
+
+    /// Compare two values like the `==` operator of the given php version
+    pub fn loose_eq_with_mode(&self, other: &PhpVal, mode: CompareMode) -> bool {
+        compare::compare(self, other, mode) == Ordering::Equal
+    }
+
+    /// Compare two values like php's `===` operator
+    ///
+    /// Since `PhpVal` doesn't keep the identity of objects, objects are identical when their properties are.
+    pub fn strict_eq(&self, other: &PhpVal) -> bool {
+        compare::identical(self, other)
+    }
+
+    /// Compare two values like php 8's `<=>` operator, as used by `sort()`
+    ///
+    /// Like php, uncomparable values such as arrays with different keys compare as `Greater`.
+    pub fn spaceship(&self, other: &PhpVal) -> Ordering {
+        self.spaceship_with_mode(other, CompareMode::Php8)
+    }
+
+    /// Compare two values like the `<=>` operator of the given php version
+    pub fn spaceship_with_mode(&self, other: &PhpVal, mode: CompareMode) -> Ordering {
+        compare::compare(self, other, mode)
+    }
 }
 
 impl Default for PhpVal {
@@ -66,37 +211,74 @@ impl Default for PhpVal {
     }
 }
 
-impl From<PhpVal> for Result<PhpVal, CastError> {
+/// Conversion of php values into rust types, used for the arguments of exported functions
+///
+/// Implement this trait to use your own types as argument.
+pub trait FromPhpVal: Sized {
+    /// The type hint php shows for arguments of this type, `Undef` for no type hint
+    const TYPE_HINT: ZValType = ZValType::Undef;
+    /// Whether arguments of this type accept `null`
+    const ALLOW_NULL: bool = false;
+    /// The types of a union type, declared instead of `TYPE_HINT` on php 8 if not empty
+    const UNION_TYPES: &'static [ZValType] = &[];
+    /// The class of accepted objects followed by a NUL byte, declared instead of `TYPE_HINT` on php 8 if not empty
+    const CLASS_NAME: &'static str = "";
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError>;
+
+    /// Convert a value following php's coercive typing rules, used when the caller didn't declare `strict_types=1`
+    ///
+    /// Defaults to the strict conversion, scalar types accept other scalars like php's own functions do.
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        Self::from_php_val(val)
+    }
+}
+
+/// Conversion of rust types into php values, used for the return values of exported functions
+///
+/// All types implementing `Into<PhpVal>` implement this trait.
+pub trait IntoPhpVal {
+    fn into_php_val(self) -> PhpVal;
+}
+
+/// Conversion of php array keys into rust types, used for the keys of map arguments
+pub trait FromArrayKey: Sized {
+    fn from_array_key(key: ArrayKey) -> Result<Self, CastError>;
+}
+
+impl<T: Into<PhpVal>> IntoPhpVal for T {
+    fn into_php_val(self) -> PhpVal {
+        self.into()
+    }
+}
+
+impl<T: FromPhpVal> From<PhpVal> for Result<T, CastError> {
     fn from(val: PhpVal) -> Self {
+        T::from_php_val(val)
+    }
+}
+
+impl FromPhpVal for PhpVal {
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
         Ok(val)
     }
 }
 
 macro_rules! impl_from_phpval {
-    ($type:ty, $variant:ident, $type2:ty) => {
-        // non nullable version
-        impl From<PhpVal> for Result<$type2, CastError> {
-            fn from(val: PhpVal) -> Self {
-                match val {
+    ($type:ty, $variant:ident, $type2:ty, $hint:expr, $coerce:path $(, $widen:ident)*) => {
+        impl FromPhpVal for $type2 {
+            const TYPE_HINT: ZValType = $hint;
+
+            fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+                match val.unwrap_reference() {
                     PhpVal::$variant(val) => Ok(val as $type2),
-                    _ => Err(CastError {
-                        actual: val.get_type(),
-                    }),
+                    $(PhpVal::$widen(val) => Ok(val as $type2),)*
+                    val => Err(CastError::new(val.get_type())),
                 }
             }
-        }
 
-        // nullable version
-        impl From<PhpVal> for Result<Option<$type2>, CastError> {
-            fn from(val: PhpVal) -> Self {
-                match val {
-                    PhpVal::Null => Ok(None),
-                    PhpVal::Undef => Ok(None),
-                    PhpVal::$variant(val) => Ok(Some(val as $type2)),
-                    _ => Err(CastError {
-                        actual: val.get_type(),
-                    }),
-                }
+            fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+                $coerce(val).map(|val| val as $type2)
             }
         }
 
@@ -108,20 +290,221 @@ macro_rules! impl_from_phpval {
     };
 }
 
-impl_from_phpval!(i64, Long, isize);
-impl_from_phpval!(i64, Long, i64);
-impl_from_phpval!(i64, Long, i32);
-impl_from_phpval!(i64, Long, i16);
-impl_from_phpval!(i64, Long, i8);
-impl_from_phpval!(i64, Long, usize);
-impl_from_phpval!(i64, Long, u64);
-impl_from_phpval!(i64, Long, u32);
-impl_from_phpval!(i64, Long, u16);
-impl_from_phpval!(i64, Long, u8);
-impl_from_phpval!(f64, Double, f64);
-impl_from_phpval!(f64, Double, f32);
-impl_from_phpval!(bool, Bool, bool);
-impl_from_phpval!(String, String, String);
+impl_from_phpval!(i64, Long, i64, ZValType::Long, juggle::coerce_long);
+// like php's strict_types, ints are still accepted for floats
+impl_from_phpval!(f64, Double, f64, ZValType::Double, juggle::coerce_double, Long);
+impl_from_phpval!(f64, Double, f32, ZValType::Double, juggle::coerce_double, Long);
+impl_from_phpval!(bool, Bool, bool, ZValType::Bool, juggle::coerce_bool);
+impl_from_phpval!(String, String, String, ZValType::String, juggle::coerce_string);
+
+trait RangeCheck: Sized {
+    fn from_long(val: i64) -> Result<Self, CastError>;
+}
+
+macro_rules! impl_from_phpval_int {
+    ($type:ty) => {
+        impl FromPhpVal for $type {
+            const TYPE_HINT: ZValType = ZValType::Long;
+
+            fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+                i64::from_php_val(val).and_then(Self::from_long)
+            }
+
+            fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+                i64::coerce_php_val(val).and_then(Self::from_long)
+            }
+        }
+
+        impl RangeCheck for $type {
+            fn from_long(val: i64) -> Result<Self, CastError> {
+                <$type>::try_from(val).map_err(|_| {
+                    CastError::invalid(
+                        ZValType::Long,
+                        format!("{} is out of range for {}", val, stringify!($type)),
+                    )
+                })
+            }
+        }
+    };
+}
+
+macro_rules! impl_into_phpval_int {
+    ($type:ty) => {
+        impl From<$type> for PhpVal {
+            fn from(input: $type) -> Self {
+                PhpVal::Long(i64::from(input))
+            }
+        }
+    };
+    ($type:ty, checked) => {
+        impl From<$type> for PhpVal {
+            fn from(input: $type) -> Self {
+                match i64::try_from(input) {
+                    Ok(val) => PhpVal::Long(val),
+                    // like php itself, integers that don't fit in a php int are converted to a float
+                    Err(_) => PhpVal::Double(input as f64),
+                }
+            }
+        }
+    };
+}
+
+impl_from_phpval_int!(isize);
+impl_from_phpval_int!(i32);
+impl_from_phpval_int!(i16);
+impl_from_phpval_int!(i8);
+impl_from_phpval_int!(usize);
+impl_from_phpval_int!(u64);
+impl_from_phpval_int!(u32);
+impl_from_phpval_int!(u16);
+impl_from_phpval_int!(u8);
+
+impl_into_phpval_int!(isize, checked);
+impl_into_phpval_int!(i32);
+impl_into_phpval_int!(i16);
+impl_into_phpval_int!(i8);
+impl_into_phpval_int!(usize, checked);
+impl_into_phpval_int!(u64, checked);
+impl_into_phpval_int!(u32);
+impl_into_phpval_int!(u16);
+impl_into_phpval_int!(u8);
+
+// nullable version
+impl<T: FromPhpVal> FromPhpVal for Option<T> {
+    const TYPE_HINT: ZValType = T::TYPE_HINT;
+    const ALLOW_NULL: bool = true;
+    const UNION_TYPES: &'static [ZValType] = T::UNION_TYPES;
+    const CLASS_NAME: &'static str = T::CLASS_NAME;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val.unwrap_reference() {
+            PhpVal::Null => Ok(None),
+            PhpVal::Undef => Ok(None),
+            val => T::from_php_val(val).map(Some),
+        }
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match val.unwrap_reference() {
+            PhpVal::Null => Ok(None),
+            PhpVal::Undef => Ok(None),
+            val => T::coerce_php_val(val).map(Some),
+        }
+    }
+}
+
+impl FromArrayKey for ArrayKey {
+    fn from_array_key(key: ArrayKey) -> Result<Self, CastError> {
+        Ok(key)
+    }
+}
+
+impl FromArrayKey for String {
+    fn from_array_key(key: ArrayKey) -> Result<Self, CastError> {
+        match key {
+            ArrayKey::String(key) => Ok(key),
+            ArrayKey::Int(key) => Ok(key.to_string()),
+        }
+    }
+}
+
+macro_rules! impl_from_array_key_int {
+    ($type:ty) => {
+        impl FromArrayKey for $type {
+            fn from_array_key(key: ArrayKey) -> Result<Self, CastError> {
+                match key {
+                    ArrayKey::Int(key) => <$type>::try_from(key).map_err(|_| {
+                        CastError::invalid(
+                            ZValType::Long,
+                            format!("{} is out of range for {}", key, stringify!($type)),
+                        )
+                    }),
+                    ArrayKey::String(_) => Err(CastError::new(ZValType::String)),
+                }
+            }
+        }
+    };
+}
+
+impl_from_array_key_int!(i64);
+impl_from_array_key_int!(i32);
+impl_from_array_key_int!(isize);
+impl_from_array_key_int!(u64);
+impl_from_array_key_int!(u32);
+impl_from_array_key_int!(usize);
+
+/// Cast all elements of a php array, recording the key of the element that failed to cast
+fn cast_array<K: FromArrayKey, T, C>(
+    val: PhpVal,
+    cast: fn(PhpVal) -> Result<T, CastError>,
+) -> Result<C, CastError>
+where
+    C: std::iter::FromIterator<(K, T)>,
+{
+    match val.unwrap_reference() {
+        PhpVal::Array(items) => items
+            .into_iter()
+            .map(|(key, value)| {
+                match (K::from_array_key(key.clone()), cast(value)) {
+                    (Ok(key), Ok(value)) => Ok((key, value)),
+                    (Err(err), _) | (_, Err(err)) => Err(err.with_key(key)),
+                }
+            })
+            .collect(),
+        val => Err(CastError::new(val.get_type())),
+    }
+}
+
+impl<T: FromPhpVal> FromPhpVal for Vec<T> {
+    const TYPE_HINT: ZValType = ZValType::Array;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        // keys are ignored, values are taken in array order
+        let items = cast_array::<ArrayKey, T, Vec<_>>(val, T::from_php_val)?;
+        Ok(items.into_iter().map(|(_, value)| value).collect())
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        let items = cast_array::<ArrayKey, T, Vec<_>>(val, T::coerce_php_val)?;
+        Ok(items.into_iter().map(|(_, value)| value).collect())
+    }
+}
+
+impl<K: FromArrayKey + Hash + Eq, T: FromPhpVal> FromPhpVal for HashMap<K, T> {
+    const TYPE_HINT: ZValType = ZValType::Array;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        cast_array(val, T::from_php_val)
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        cast_array(val, T::coerce_php_val)
+    }
+}
+
+impl<K: FromArrayKey + Hash + Eq, T: FromPhpVal> FromPhpVal for IndexMap<K, T> {
+    const TYPE_HINT: ZValType = ZValType::Array;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        cast_array(val, T::from_php_val)
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        cast_array(val, T::coerce_php_val)
+    }
+}
+
+impl<K: FromArrayKey + Ord, T: FromPhpVal> FromPhpVal for BTreeMap<K, T> {
+    const TYPE_HINT: ZValType = ZValType::Array;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        cast_array(val, T::from_php_val)
+    }
+
+    fn coerce_php_val(val: PhpVal) -> Result<Self, CastError> {
+        cast_array(val, T::coerce_php_val)
+    }
+}
 
 impl From<()> for PhpVal {
     fn from(_input: ()) -> Self {
@@ -129,39 +512,39 @@ impl From<()> for PhpVal {
     }
 }
 
-impl<T: Into<PhpVal>> From<Option<T>> for PhpVal {
+impl<T: IntoPhpVal> From<Option<T>> for PhpVal {
     fn from(input: Option<T>) -> Self {
         match input {
-            Some(inner) => inner.into(),
+            Some(inner) => inner.into_php_val(),
             None => PhpVal::Null,
         }
     }
 }
 
-impl<T: Into<PhpVal>> From<Vec<T>> for PhpVal {
+impl<T: IntoPhpVal> From<Vec<T>> for PhpVal {
     fn from(input: Vec<T>) -> Self {
         PhpVal::Array(
             input
                 .into_iter()
                 .enumerate()
-                .map(|(key, value)| (key.into(), value.into()))
+                .map(|(key, value)| (key.into(), value.into_php_val()))
                 .collect(),
         )
     }
 }
 
-impl<K: Into<ArrayKey>, T: Into<PhpVal>> From<Vec<(K, T)>> for PhpVal {
+impl<K: Into<ArrayKey>, T: IntoPhpVal> From<Vec<(K, T)>> for PhpVal {
     fn from(input: Vec<(K, T)>) -> Self {
         PhpVal::Array(
             input
                 .into_iter()
-                .map(|(key, value)| (key.into(), value.into()))
+                .map(|(key, value)| (key.into(), value.into_php_val()))
                 .collect(),
         )
     }
 }
 
-impl<K: Into<ArrayKey> + Hash + Eq + Ord, T: Into<PhpVal>> From<HashMap<K, T>> for PhpVal {
+impl<K: Into<ArrayKey> + Hash + Eq + Ord, T: IntoPhpVal> From<HashMap<K, T>> for PhpVal {
     fn from(input: HashMap<K, T>) -> Self {
         let mut vec: Vec<(K, T)> = input.into_iter().collect();
         // since hashmap doesn't contain any stable order we sort it to get predictable results
@@ -169,3 +552,25 @@ impl<K: Into<ArrayKey> + Hash + Eq + Ord, T: Into<PhpVal>> From<HashMap<K, T>> f
         vec.into()
     }
 }
+
+impl<K: Into<ArrayKey>, T: IntoPhpVal> From<IndexMap<K, T>> for PhpVal {
+    fn from(input: IndexMap<K, T>) -> Self {
+        PhpVal::Array(
+            input
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into_php_val()))
+                .collect(),
+        )
+    }
+}
+
+impl<K: Into<ArrayKey>, T: IntoPhpVal> From<BTreeMap<K, T>> for PhpVal {
+    fn from(input: BTreeMap<K, T>) -> Self {
+        PhpVal::Array(
+            input
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into_php_val()))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,148 @@
+//! Php stubs declaring the exported functions
+//!
+//! `ivory_module!` generates a `php_stubs()` function returning the stubs of all exported functions and enums,
+//! for IDEs and static analysis tools which can't inspect the extension itself.
+
+use crate::error::php_type_name;
+use crate::zend::{PhpEnum, ZValType};
+use crate::PhpVal;
+
+/// An exported function as declared in the stubs
+pub struct StubFunction {
+    pub name: &'static str,
+    pub args: &'static [StubArg],
+}
+
+/// An argument of an exported function as declared in the stubs
+pub struct StubArg {
+    pub name: &'static str,
+    /// the type hint of the argument, `Undef` for no type hint
+    pub ty: ZValType,
+    /// the class of accepted objects followed by a NUL byte, declared instead of `ty` if not empty
+    pub class_name: &'static str,
+    /// the types of a union type, declared instead of `ty` if not empty
+    pub union_types: &'static [ZValType],
+    pub allow_null: bool,
+    pub by_reference: bool,
+    pub is_variadic: bool,
+    /// the default value as php code, `UNKNOWN` if it can't be expressed in php
+    pub default: Option<&'static str>,
+}
+
+/// A php 8.1 enum as declared in the stubs
+pub struct StubEnum {
+    pub name: &'static str,
+    /// the names of the cases with their backing values, `None` for pure enums
+    pub cases: Vec<(&'static str, Option<PhpVal>)>,
+}
+
+impl StubEnum {
+    pub fn of<T: PhpEnum>() -> Self {
+        StubEnum {
+            name: T::NAME,
+            cases: T::cases(),
+        }
+    }
+}
+
+/// Render the stubs of the functions and enums as a php file
+pub fn render_stubs(functions: &[StubFunction], enums: &[StubEnum]) -> String {
+    let mut stubs = String::from("<?php\n");
+    for php_enum in enums {
+        stubs.push_str(&render_enum(php_enum));
+    }
+    for function in functions {
+        let args: Vec<String> = function.args.iter().map(render_arg).collect();
+        stubs.push_str(&format!("\nfunction {}({}) {{}}\n", function.name, args.join(", ")));
+    }
+    stubs
+}
+
+fn render_enum(php_enum: &StubEnum) -> String {
+    let backing_type = match php_enum.cases.first() {
+        Some((_, Some(value))) => format!(": {}", php_type_name(value.get_type())),
+        _ => String::new(),
+    };
+    let mut declaration = format!("\nenum {}{}\n{{\n", php_enum.name, backing_type);
+    for (case, value) in &php_enum.cases {
+        match value {
+            Some(PhpVal::String(value)) => declaration.push_str(&format!(
+                "    case {} = '{}';\n",
+                case,
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            )),
+            Some(value) => declaration.push_str(&format!("    case {} = {};\n", case, value.to_php_string())),
+            None => declaration.push_str(&format!("    case {};\n", case)),
+        }
+    }
+    declaration.push_str("}\n");
+    declaration
+}
+
+fn render_arg(arg: &StubArg) -> String {
+    let mut declaration = String::new();
+    if !arg.class_name.is_empty() {
+        if arg.allow_null {
+            declaration.push('?');
+        }
+        declaration.push_str(arg.class_name.trim_end_matches('\0'));
+        declaration.push(' ');
+    } else if let Some(types) = declared_union(arg.union_types) {
+        declaration.push_str(&types.join("|"));
+        if arg.allow_null {
+            declaration.push_str("|null");
+        }
+        declaration.push(' ');
+    } else if let Some(ty) = declared_type(arg.ty) {
+        if arg.allow_null {
+            declaration.push('?');
+        }
+        declaration.push_str(&ty);
+        declaration.push(' ');
+    }
+    if arg.by_reference {
+        declaration.push('&');
+    }
+    if arg.is_variadic {
+        declaration.push_str("...");
+    }
+    declaration.push('$');
+    declaration.push_str(arg.name);
+    if let Some(default) = arg.default {
+        declaration.push_str(" = ");
+        declaration.push_str(default);
+    }
+    declaration
+}
+
+/// The names of the types of a union if they can all be used as parameter types
+fn declared_union(types: &[ZValType]) -> Option<Vec<String>> {
+    if types.is_empty() {
+        return None;
+    }
+    let mut names: Vec<String> = Vec::new();
+    for ty in types {
+        let name = declared_type(*ty)?;
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Some(names)
+}
+
+/// The name of a type if it can be used as a parameter type
+fn declared_type(ty: ZValType) -> Option<String> {
+    match ty {
+        ZValType::Long
+        | ZValType::Double
+        | ZValType::String
+        | ZValType::Bool
+        | ZValType::True
+        | ZValType::False
+        | ZValType::Array
+        | ZValType::Object
+        | ZValType::Callable
+        | ZValType::Iterable => Some(php_type_name(ty)),
+        _ => None,
+    }
+}
@@ -1,25 +1,26 @@
 use std::alloc::{alloc, Layout};
 use std::cmp::max;
 use std::mem::size_of;
-use std::ptr;
+use std::os::raw::c_char;
+use std::{ptr, slice};
 
 use ivory_sys::*;
 
-use crate::zend::string::{construct_zend_string, parse_zend_string};
+use crate::zend::string::{borrow_zend_string, construct_zend_string, parse_zend_string};
 use crate::zend::{ZVal, ZValType};
-use crate::{ArrayKey, PhpVal};
+use crate::{ArrayKey, ArrayKeyRef, CastError, PhpVal};
 
 pub(super) unsafe fn parse_zend_array(arr: zend_array) -> Vec<(ArrayKey, PhpVal)> {
     let len = arr.nNumUsed;
     let mut result = Vec::new();
     for i in 0..len {
-        let elem = *arr.arData.add(i as usize);
+        let elem = &*arr.arData.add(i as usize);
         let key = if elem.key.is_null() {
-            ArrayKey::Int(elem.h)
+            ArrayKey::Int(elem.h as i64)
         } else {
             ArrayKey::String(parse_zend_string(&*elem.key))
         };
-        let val: PhpVal = ZVal::from(elem.val).as_php_val();
+        let val: PhpVal = ZVal::from_raw(&elem.val).as_php_val();
         match val {
             PhpVal::Undef => {}
             _ => result.push((key, val)),
@@ -28,6 +29,138 @@ pub(super) unsafe fn parse_zend_array(arr: zend_array) -> Vec<(ArrayKey, PhpVal)
     result
 }
 
+/// A borrowed php array, elements are only converted when they are accessed
+///
+/// Taking an array argument as `ZArray` instead of a `Vec` or `PhpArray` avoids copying the whole array
+/// when a function only needs a few of its elements.
+#[derive(Clone, Copy)]
+pub struct ZArray<'a> {
+    table: &'a zend_array,
+}
+
+impl<'a> ZArray<'a> {
+    /// Borrow the array held by a zval, references are followed
+    pub fn from_zval(zval: &'a ZVal) -> Result<Self, CastError> {
+        let zval = zval.deref_indirect();
+        let zval = match zval.reference_target() {
+            Some(target) => unsafe { ZVal::from_raw(target) },
+            None => zval,
+        };
+        match zval.array() {
+            Some(table) => Ok(ZArray {
+                table: unsafe { &*table },
+            }),
+            None => Err(CastError::new(zval.get_type())),
+        }
+    }
+
+    /// Borrow an array argument, `arg` is `None` if the argument was omitted
+    pub fn from_arg(arg: Option<&'a ZVal>) -> Result<Self, CastError> {
+        match arg {
+            Some(zval) => ZArray::from_zval(zval),
+            None => Err(CastError::new(ZValType::Undef)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.nNumOfElements as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Look up a key using the engine's hash lookup
+    pub fn get<K: Into<ArrayKey>>(&self, key: K) -> Option<&'a ZVal> {
+        let found = unsafe {
+            match key.into() {
+                ArrayKey::Int(index) => zend_hash_index_find(self.table, index as zend_ulong),
+                ArrayKey::String(key) => {
+                    zend_hash_str_find(self.table, key.as_ptr() as *const c_char, key.len())
+                }
+            }
+        };
+        if found.is_null() {
+            return None;
+        }
+        let val = unsafe { ZVal::from_raw(found) }.deref_indirect();
+        match val.get_type() {
+            ZValType::Undef => None,
+            _ => Some(val),
+        }
+    }
+
+    pub fn contains_key<K: Into<ArrayKey>>(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Iterate over the elements in order without copying the values
+    pub fn iter(&self) -> ZArrayIter<'a> {
+        let buckets = match self.table.nNumUsed {
+            0 => &[],
+            used => unsafe { slice::from_raw_parts(self.table.arData, used as usize) },
+        };
+        ZArrayIter {
+            buckets: buckets.iter(),
+        }
+    }
+
+    /// Copy the whole array
+    pub fn to_vec(&self) -> Vec<(ArrayKey, PhpVal)> {
+        unsafe { parse_zend_array(*self.table) }
+    }
+}
+
+impl<'a> IntoIterator for ZArray<'a> {
+    type Item = (ArrayKeyRef<'a>, &'a ZVal);
+    type IntoIter = ZArrayIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &ZArray<'a> {
+    type Item = (ArrayKeyRef<'a>, &'a ZVal);
+    type IntoIter = ZArrayIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the keys and values of a `ZArray`
+///
+/// String keys are borrowed from the array, convert them into an `ArrayKey` to keep them.
+pub struct ZArrayIter<'a> {
+    buckets: slice::Iter<'a, Bucket>,
+}
+
+impl<'a> Iterator for ZArrayIter<'a> {
+    type Item = (ArrayKeyRef<'a>, &'a ZVal);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for bucket in &mut self.buckets {
+            let val = unsafe { ZVal::from_raw(&bucket.val) }.deref_indirect();
+            // removed elements are left in place as undefined until the table is compacted
+            if val.get_type() == ZValType::Undef {
+                continue;
+            }
+            let key = if bucket.key.is_null() {
+                ArrayKeyRef::Int(bucket.h as i64)
+            } else {
+                ArrayKeyRef::String(unsafe { borrow_zend_string(bucket.key) })
+            };
+            return Some((key, val));
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.buckets.len()))
+    }
+}
+
 // WIP pure rust implementation of array construction in order to be able to run conversion test without linking to php
 pub(super) fn create_zend_array(vec: Vec<(ArrayKey, PhpVal)>) -> zend_array {
     let bucket_size = size_of::<Bucket>();
@@ -58,9 +191,9 @@ pub(super) fn create_zend_array(vec: Vec<(ArrayKey, PhpVal)>) -> zend_array {
 
     for (key, val) in vec.into_iter() {
         unsafe {
-            match key {
+            match key.normalize() {
                 ArrayKey::Int(key) => {
-                    (*curr_bucket).h = key;
+                    (*curr_bucket).h = key as zend_ulong;
                     (*curr_bucket).key = ptr::null_mut()
                 }
                 ArrayKey::String(key) => {
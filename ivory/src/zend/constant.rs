@@ -0,0 +1,43 @@
+use std::os::raw::{c_char, c_int};
+
+use ivory_sys::*;
+
+use crate::PhpVal;
+
+// CONST_CS | CONST_PERSISTENT
+const CONSTANT_FLAGS: c_int = 3;
+
+/// A type that provides php constants, implemented by `#[derive(FromPhp)]` for enums with `#[php(constants)]`
+pub trait PhpConstants {
+    fn constants() -> Vec<(String, PhpVal)>;
+}
+
+/// Register the constants of a type, called from the module startup generated by `ivory_module!`
+pub fn register_constants<T: PhpConstants>(module_number: c_int) {
+    for (name, value) in T::constants() {
+        register_constant(&name, value, module_number);
+    }
+}
+
+/// Register a php constant with an int or string value
+pub fn register_constant(name: &str, value: PhpVal, module_number: c_int) {
+    // zend copies the name and value while registering
+    let name_ptr = name.as_ptr() as *const c_char;
+    match value {
+        PhpVal::Long(value) => unsafe {
+            zend_register_long_constant(name_ptr, name.len(), value, CONSTANT_FLAGS, module_number);
+        },
+        PhpVal::String(value) => unsafe {
+            // the length is passed explicitly so values containing NUL bytes are kept intact
+            zend_register_stringl_constant(
+                name_ptr,
+                name.len(),
+                value.as_ptr() as *mut c_char,
+                value.len(),
+                CONSTANT_FLAGS,
+                module_number,
+            );
+        },
+        value => panic!("unsupported constant type {}", value.get_type()),
+    }
+}
@@ -0,0 +1,145 @@
+use std::os::raw::c_int;
+
+use crate::zend::ZVal;
+use crate::PhpVal;
+
+#[cfg(php81)]
+use std::ffi::CString;
+#[cfg(php81)]
+use std::ptr;
+
+#[cfg(php81)]
+use ivory_sys::*;
+
+#[cfg(php81)]
+use crate::externs::throw_value_error;
+#[cfg(not(php81))]
+use crate::externs::throw_error;
+#[cfg(php81)]
+use crate::zend::string::borrow_zend_string;
+#[cfg(php81)]
+use crate::zend::ZValType;
+
+// flag of the class constants holding the cases of an enum
+#[cfg(php81)]
+const ZEND_CLASS_CONST_IS_CASE: u32 = 1 << 6;
+
+/// A fieldless rust enum registered as php 8.1 enum, implemented by `#[ivory_enum]`
+pub trait PhpEnum {
+    /// The name of the enum class
+    const NAME: &'static str;
+
+    /// The names of the cases in declaration order with their backing values, `None` for pure enums
+    fn cases() -> Vec<(&'static str, Option<PhpVal>)>;
+}
+
+/// Register the enum class of a type, called from the module startup generated by `ivory_module!`
+#[cfg(php81)]
+pub fn register_enum<T: PhpEnum>(_module_number: c_int) {
+    let cases = T::cases();
+    let backing_type = match cases.first() {
+        Some((_, Some(value))) => value.get_type(),
+        _ => ZValType::Undef,
+    };
+    // class and case names are identifiers, which can't contain NUL bytes
+    let name = CString::new(T::NAME).unwrap();
+    unsafe {
+        let class = zend_register_internal_enum(name.as_ptr(), backing_type.code(), ptr::null());
+        for (case, value) in cases {
+            let case = CString::new(case).unwrap();
+            match value {
+                Some(value) => {
+                    // the case takes ownership of the value
+                    let mut value = backing_value(value).into_raw();
+                    zend_enum_add_case_cstr(class, case.as_ptr(), &mut value);
+                }
+                None => zend_enum_add_case_cstr(class, case.as_ptr(), ptr::null_mut()),
+            }
+        }
+    }
+}
+
+/// Php enums need php 8.1, `#[ivory_enum]` refuses to compile for older versions
+#[cfg(not(php81))]
+pub fn register_enum<T: PhpEnum>(_module_number: c_int) {
+    panic!("enum {} can't be registered, php enums need php 8.1", T::NAME);
+}
+
+/// The backing value of a case, string values need to be interned
+#[cfg(php81)]
+unsafe fn backing_value(value: PhpVal) -> ZVal {
+    match value {
+        PhpVal::String(value) => {
+            let init_interned = zend_string_init_interned.expect("zend_string_init_interned is not set");
+            ZVal::from_zend_string(init_interned(value.as_ptr() as *const _, value.len(), true))
+        }
+        value => value.into(),
+    }
+}
+
+/// Read the case of an enum object, `None` if the object isn't an enum case
+#[cfg(php81)]
+pub(super) unsafe fn parse_enum_case(obj: *mut zend_object) -> Option<PhpVal> {
+    let class = (*obj).ce;
+    if (*class).ce_flags & ZEND_ACC_ENUM == 0 {
+        return None;
+    }
+    // the name of the case is stored in the first property
+    let name = ZVal::from_raw((*obj).properties_table.as_ptr());
+    Some(PhpVal::EnumCase {
+        class: borrow_zend_string((*class).name).to_string(),
+        case: name.as_str(),
+    })
+}
+
+#[cfg(not(php81))]
+pub(super) unsafe fn parse_enum_case(_obj: *mut ivory_sys::zend_object) -> Option<PhpVal> {
+    None
+}
+
+/// Get the case object of an enum class, throwing a `ValueError` if there is no such case
+#[cfg(php81)]
+pub(super) fn enum_case_zval(class: &str, case: &str) -> ZVal {
+    match find_enum_case(class, case) {
+        Some(case) => case,
+        None => {
+            throw_value_error(format!("{}::{} is not an enum case", class, case));
+            PhpVal::Null.into()
+        }
+    }
+}
+
+#[cfg(not(php81))]
+pub(super) fn enum_case_zval(class: &str, case: &str) -> ZVal {
+    throw_error(format!("{}::{} can't be created, php enums need php 8.1", class, case));
+    PhpVal::Null.into()
+}
+
+/// Look up the case of a loaded enum class, adding a reference to it
+#[cfg(php81)]
+fn find_enum_case(class: &str, case: &str) -> Option<ZVal> {
+    // the class table is keyed by the lowercase name without leading backslash
+    let key = class.trim_start_matches('\\').to_ascii_lowercase();
+    let case_name = CString::new(case).ok()?;
+    unsafe {
+        let entry = zend_hash_str_find(executor_globals.class_table, key.as_ptr() as *const _, key.len());
+        if entry.is_null() {
+            return None;
+        }
+        let class = (*entry).value.ce;
+        if (*class).ce_flags & ZEND_ACC_ENUM == 0 {
+            return None;
+        }
+        // the engine asserts that the constant exists and is a case
+        let constant = zend_hash_str_find(&(*class).constants_table, case.as_ptr() as *const _, case.len());
+        if constant.is_null() {
+            return None;
+        }
+        let constant = (*constant).value.ptr as *const zend_class_constant;
+        if (*constant).value.u2.constant_flags & ZEND_CLASS_CONST_IS_CASE == 0 {
+            return None;
+        }
+        let obj = zend_enum_get_case_cstr(class, case_name.as_ptr());
+        Some(ZVal::from_object(obj))
+    }
+}
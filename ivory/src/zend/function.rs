@@ -1,21 +1,103 @@
 use crate::zend::zval::GetTypeHint;
 use crate::zend::ZValType;
 use std;
-use std::os::raw::{c_char, c_uchar, c_void};
+#[cfg(not(php8))]
+use std::os::raw::c_uchar;
+use std::os::raw::{c_char, c_void};
 
+/// Argument info as laid out by php 7.2 up to 7.4, `type_` holds the type code and whether `null` is allowed
+#[cfg(not(php8))]
 #[derive(Clone)]
 #[repr(C)]
 pub struct ArgInfo {
     pub name: *const c_char,
-    pub class_name: *const c_char,
-    // *const c_char
-    pub type_hint: ZValType,
-    // *const c_char
+    pub type_: usize,
     pub pass_by_reference: c_uchar,
-    pub allow_null: c_uchar,
     pub is_variadic: c_uchar,
 }
 
+/// Argument info as laid out by php 8, by-reference and variadic are flags of the type mask
+#[cfg(php8)]
+#[derive(Clone)]
+#[repr(C)]
+pub struct ArgInfo {
+    pub name: *const c_char,
+    pub type_: ArgType,
+    pub default_value: *const c_char,
+}
+
+/// A php 8 `zend_type` without a class name
+#[cfg(php8)]
+#[derive(Clone)]
+#[repr(C)]
+pub struct ArgType {
+    pub ptr: *const c_void,
+    pub type_mask: u32,
+}
+
+// the flags stored above the `MAY_BE_*` bits of the type mask
+#[cfg(php8)]
+const TYPE_EXTRA_FLAGS_SHIFT: u32 = if cfg!(php81) { 25 } else { 24 };
+// set if `ptr` of the type is the name of a class
+#[cfg(php8)]
+const TYPE_NAME_BIT: u32 = 1 << (TYPE_EXTRA_FLAGS_SHIFT - 1);
+#[cfg(php8)]
+const SEND_BY_REFERENCE: u32 = 1 << TYPE_EXTRA_FLAGS_SHIFT;
+#[cfg(php8)]
+const IS_VARIADIC: u32 = 1 << (TYPE_EXTRA_FLAGS_SHIFT + 2);
+
+/// The `MAY_BE_*` bits php 8 uses for a type hint, 0 for no type hint
+#[cfg(php8)]
+const fn type_mask(ty: ZValType) -> u32 {
+    match ty {
+        ZValType::Undef => 0,
+        ZValType::Bool => type_mask(ZValType::False) | type_mask(ZValType::True),
+        ZValType::Number => type_mask(ZValType::Long) | type_mask(ZValType::Double),
+        ZValType::Null
+        | ZValType::False
+        | ZValType::True
+        | ZValType::Long
+        | ZValType::Double
+        | ZValType::String
+        | ZValType::Array
+        | ZValType::Object
+        | ZValType::Resource
+        | ZValType::Callable
+        | ZValType::Iterable
+        | ZValType::Void
+        | ZValType::Static
+        | ZValType::Never => 1 << ty.code(),
+        // mixed is any type including null
+        ZValType::Mixed => {
+            type_mask(ZValType::Null)
+                | type_mask(ZValType::Bool)
+                | type_mask(ZValType::Long)
+                | type_mask(ZValType::Double)
+                | type_mask(ZValType::String)
+                | type_mask(ZValType::Array)
+                | type_mask(ZValType::Object)
+                | type_mask(ZValType::Resource)
+        }
+        _ => 0,
+    }
+}
+
+/// The `MAY_BE_*` bits of a union type, 0 if any of the types has no type hint
+#[cfg(php8)]
+const fn union_mask(types: &[ZValType]) -> u32 {
+    let mut mask = 0;
+    let mut i = 0;
+    while i < types.len() {
+        let ty_mask = type_mask(types[i]);
+        if ty_mask == 0 {
+            return 0;
+        }
+        mask |= ty_mask;
+        i += 1;
+    }
+    mask
+}
+
 impl ArgInfo {
     pub const fn new(
         name: *const c_char,
@@ -24,22 +106,90 @@ impl ArgInfo {
         is_variadic: bool,
         by_reference: bool,
     ) -> ArgInfo {
+        ArgInfo::typed(name, ty, "", &[], allow_null, is_variadic, by_reference)
+    }
+
+    /// An argument accepting instances of `class_name`, any of the `union_types` or `ty`, in that order
+    ///
+    /// The class name has to be NUL terminated, an empty class name declares no class.
+    /// Php 7 builds only declare `ty`, ignoring the class and union types.
+    #[cfg(not(php8))]
+    pub const fn typed(
+        name: *const c_char,
+        ty: ZValType,
+        _class_name: &'static str,
+        _union_types: &[ZValType],
+        allow_null: bool,
+        is_variadic: bool,
+        by_reference: bool,
+    ) -> ArgInfo {
+        let type_ = match ty {
+            ZValType::Undef => 0,
+            ty => ((ty.code() as usize) << 2) | allow_null as usize,
+        };
         ArgInfo {
             name,
-            class_name: std::ptr::null(),
-            type_hint: ty,
+            type_,
             pass_by_reference: by_reference as c_uchar,
-            allow_null: allow_null as c_uchar,
             is_variadic: is_variadic as c_uchar,
         }
     }
 
+    /// An argument accepting instances of `class_name`, any of the `union_types` or `ty`, in that order
+    ///
+    /// The class name has to be NUL terminated, an empty class name declares no class.
+    #[cfg(php8)]
+    pub const fn typed(
+        name: *const c_char,
+        ty: ZValType,
+        class_name: &'static str,
+        union_types: &[ZValType],
+        allow_null: bool,
+        is_variadic: bool,
+        by_reference: bool,
+    ) -> ArgInfo {
+        let mut mask = if !class_name.is_empty() {
+            TYPE_NAME_BIT
+        } else if union_types.is_empty() {
+            type_mask(ty)
+        } else {
+            union_mask(union_types)
+        };
+        // an argument without type hint accepts null anyway
+        if mask != 0 && allow_null {
+            mask |= type_mask(ZValType::Null);
+        }
+        if by_reference {
+            mask |= SEND_BY_REFERENCE;
+        }
+        if is_variadic {
+            mask |= IS_VARIADIC;
+        }
+        ArgInfo {
+            name,
+            type_: ArgType {
+                // the engine replaces the class name with an interned string while registering the function
+                ptr: if class_name.is_empty() {
+                    std::ptr::null()
+                } else {
+                    class_name.as_ptr() as *const c_void
+                },
+                type_mask: mask,
+            },
+            default_value: std::ptr::null(),
+        }
+    }
+
     pub const fn arg_count(count: usize) -> Self {
         ArgInfo::new(count as *const c_char, ZValType::Undef, false, false, false)
     }
 
     pub const fn from_type<T>(name: *const c_char, is_ref: bool) -> Self {
-        ArgInfo::new(name, ZValType::Undef, false, false, false)
+        ArgInfo::new(name, ZValType::Undef, false, false, is_ref)
+    }
+
+    pub const fn variadic<T>(name: *const c_char, is_ref: bool) -> Self {
+        ArgInfo::new(name, ZValType::Undef, false, true, is_ref)
     }
 }
 
@@ -49,13 +199,7 @@ pub trait GetArgInfo {
 
 impl<T: GetTypeHint> GetArgInfo for T {
     fn get_arg_info(name: *const c_char, is_ref: bool) -> ArgInfo {
-        ArgInfo::new(name, Self::get_type_hint(), false, false, is_ref)
-    }
-}
-
-impl<T: GetTypeHint> GetArgInfo for Option<T> {
-    fn get_arg_info(name: *const c_char, is_ref: bool) -> ArgInfo {
-        ArgInfo::new(name, T::get_type_hint(), true, false, is_ref)
+        ArgInfo::new(name, Self::get_type_hint(), Self::allow_null(), false, is_ref)
     }
 }
 
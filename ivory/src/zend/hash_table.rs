@@ -0,0 +1,265 @@
+use std::any::Any;
+use std::cmp::Ordering;
+use std::mem::ManuallyDrop;
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+use ivory_sys::*;
+
+use crate::args::FromArg;
+use crate::externs;
+use crate::zend::zval::{GC_IMMUTABLE, IS_TYPE_COLLECTABLE, IS_TYPE_REFCOUNTED};
+use crate::zend::{IntoZVal, ZArray, ZArrayIter, ZVal, ZValType};
+use crate::{ArrayKey, ArrayKeyRef, CastError, PhpVal};
+
+/// A php array that can be modified in place
+///
+/// Taking a `&mut ZHashTable` argument passes the array by reference, changes are made directly
+/// to the variable of the caller without converting the array.
+/// A `ZHashTable` can also be created with `new` and returned from an exported function.
+///
+/// Like php's `SEPARATE_ARRAY`, arrays that are shared with other variables or immutable are copied
+/// before the first write, so changes never leak into other copies of the array.
+pub struct ZHashTable {
+    table: Table,
+}
+
+enum Table {
+    Owned(ZVal),
+    /// the zval inside a php reference
+    Borrowed(*mut zval),
+}
+
+impl ZHashTable {
+    pub fn new() -> Self {
+        ZHashTable {
+            table: Table::Owned(ZVal::from(PhpVal::Array(Vec::new()))),
+        }
+    }
+
+    fn zval(&self) -> &ZVal {
+        match &self.table {
+            Table::Owned(zval) => zval,
+            Table::Borrowed(target) => unsafe { ZVal::from_raw(*target) },
+        }
+    }
+
+    /// Get the table for writing, copying it first if it's shared or immutable
+    fn separate(&mut self) -> *mut zend_array {
+        let zval = match &mut self.table {
+            Table::Owned(zval) => zval.as_mut_ptr(),
+            Table::Borrowed(target) => *target,
+        };
+        unsafe {
+            let table = (*zval).value.arr;
+            if (*table).gc.refcount > 1 || (*table).gc.u.type_info & GC_IMMUTABLE != 0 {
+                // immutable arrays aren't refcounted by the zval holding them
+                if (*zval).u1.v.type_flags & IS_TYPE_REFCOUNTED != 0 {
+                    (*table).gc.refcount -= 1;
+                }
+                (*zval).value.arr = zend_array_dup(table);
+                (*zval).u1.v.type_flags = IS_TYPE_REFCOUNTED | IS_TYPE_COLLECTABLE;
+            }
+            (*zval).value.arr
+        }
+    }
+
+    /// Borrow the array for reading
+    pub fn as_array(&self) -> ZArray<'_> {
+        ZArray::from_zval(self.zval()).expect("hash table doesn't hold an array")
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_array().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get<K: Into<ArrayKey>>(&self, key: K) -> Option<&ZVal> {
+        self.as_array().get(key)
+    }
+
+    pub fn contains_key<K: Into<ArrayKey>>(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> ZArrayIter<'_> {
+        self.as_array().iter()
+    }
+
+    /// Add a value if the key doesn't exist yet, returns `false` if the key was already set
+    pub fn insert<K: Into<ArrayKey>, T: IntoZVal>(&mut self, key: K, value: T) -> bool {
+        let table = self.separate();
+        let mut value = ManuallyDrop::new(value.into_zval());
+        let stored = unsafe {
+            match key.into().normalize() {
+                ArrayKey::Int(index) => zend_hash_index_add(table, index as zend_ulong, value.as_mut_ptr()),
+                ArrayKey::String(key) => {
+                    zend_hash_str_add(table, key.as_ptr() as *const c_char, key.len(), value.as_mut_ptr())
+                }
+            }
+        };
+        if stored.is_null() {
+            // the table didn't take ownership of the value
+            drop(ManuallyDrop::into_inner(value));
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Set the value of a key, the previous value is released
+    pub fn update<K: Into<ArrayKey>, T: IntoZVal>(&mut self, key: K, value: T) {
+        let table = self.separate();
+        // the table takes ownership of the value
+        let mut value = ManuallyDrop::new(value.into_zval());
+        unsafe {
+            match key.into().normalize() {
+                ArrayKey::Int(index) => {
+                    zend_hash_index_update(table, index as zend_ulong, value.as_mut_ptr());
+                }
+                ArrayKey::String(key) => {
+                    zend_hash_str_update(table, key.as_ptr() as *const c_char, key.len(), value.as_mut_ptr());
+                }
+            }
+        }
+    }
+
+    /// Remove a key, returns `false` if the key wasn't set
+    pub fn remove<K: Into<ArrayKey>>(&mut self, key: K) -> bool {
+        let key = key.into().normalize();
+        if !self.contains_key(key.clone()) {
+            // avoid copying a shared array when there is nothing to remove
+            return false;
+        }
+        let table = self.separate();
+        let result = unsafe {
+            match key {
+                ArrayKey::Int(index) => zend_hash_index_del(table, index as zend_ulong),
+                ArrayKey::String(key) => zend_hash_str_del(table, key.as_ptr() as *const c_char, key.len()),
+            }
+        };
+        result == 0
+    }
+
+    /// Append a value using the next free integer index, the same as `$array[] = $value`
+    ///
+    /// Returns the index of the new element or `None` if the largest possible index is already used.
+    pub fn push<T: IntoZVal>(&mut self, value: T) -> Option<i64> {
+        let table = self.separate();
+        let mut value = ManuallyDrop::new(value.into_zval());
+        let stored = unsafe { zend_hash_next_index_insert(table, value.as_mut_ptr()) };
+        if stored.is_null() {
+            drop(ManuallyDrop::into_inner(value));
+            None
+        } else {
+            // the value is the first field of the bucket it's stored in, which also holds the key
+            Some(unsafe { (*(stored as *const Bucket)).h } as i64)
+        }
+    }
+
+    /// Sort the values with a comparison function, like php's `uasort`
+    ///
+    /// With `renumber` the keys are replaced by consecutive integers, like php's `usort`.
+    ///
+    /// A panic in the comparison function is thrown as an `Error` exception instead of unwinding into php,
+    /// the array keeps all its elements but is left partially sorted.
+    pub fn sort_by<F: FnMut(&ZVal, &ZVal) -> Ordering>(&mut self, mut compare: F, renumber: bool) {
+        let table = self.separate();
+        unsafe {
+            if (*table).nNumOfElements > 1 {
+                let buckets = slice::from_raw_parts_mut((*table).arData, (*table).nNumUsed as usize);
+                // the sort panics for comparison functions that aren't a total order,
+                // it keeps every bucket in the slice when it does
+                let sorted = panic::catch_unwind(AssertUnwindSafe(|| {
+                    // removed elements are moved to the end, the engine drops them while rebuilding the table
+                    buckets.sort_by(|a, b| {
+                        let a = ZVal::from_raw(&a.val);
+                        let b = ZVal::from_raw(&b.val);
+                        match (a.get_type(), b.get_type()) {
+                            (ZValType::Undef, ZValType::Undef) => Ordering::Equal,
+                            (ZValType::Undef, _) => Ordering::Greater,
+                            (_, ZValType::Undef) => Ordering::Less,
+                            _ => compare(a.deref_indirect(), b.deref_indirect()),
+                        }
+                    })
+                }));
+                if let Err(payload) = sorted {
+                    externs::throw_error(format!("Sorting failed: {}", panic_message(&*payload)));
+                }
+            }
+            // the buckets are already in order, the engine only needs to rebuild the hash
+            zend_hash_sort_ex(table, Some(keep_order), None, renumber as zend_bool);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        let table = self.separate();
+        unsafe { zend_hash_clean(table) };
+    }
+}
+
+impl Default for ZHashTable {
+    fn default() -> Self {
+        ZHashTable::new()
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "the comparison function panicked"
+    }
+}
+
+unsafe extern "C" fn keep_order(
+    _base: *mut c_void,
+    _count: usize,
+    _size: usize,
+    _compare: compare_func_t,
+    _swap: swap_func_t,
+) {
+}
+
+impl<'a> IntoIterator for &'a ZHashTable {
+    type Item = (ArrayKeyRef<'a>, &'a ZVal);
+    type IntoIter = ZArrayIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Arrays passed by value are copied on the first write, so changes are only visible to the function itself
+impl FromArg for ZHashTable {
+    fn from_arg(arg: Option<&ZVal>, _strict: bool) -> Result<Self, CastError> {
+        let arg = match arg {
+            Some(arg) => arg.deref_indirect(),
+            None => return Err(CastError::new(ZValType::Undef)),
+        };
+        let table = match arg.reference_target() {
+            Some(target) => Table::Borrowed(target),
+            None => Table::Owned(arg.clone()),
+        };
+        let table = ZHashTable { table };
+        match table.zval().get_type() {
+            ZValType::Array => Ok(table),
+            ty => Err(CastError::new(ty)),
+        }
+    }
+}
+
+impl IntoZVal for ZHashTable {
+    fn into_zval(self) -> ZVal {
+        match self.table {
+            Table::Owned(zval) => zval,
+            Table::Borrowed(target) => unsafe { ZVal::from_raw(target) }.clone(),
+        }
+    }
+}
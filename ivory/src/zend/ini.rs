@@ -0,0 +1,76 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use ivory_sys::*;
+
+// ZEND_INI_USER | ZEND_INI_PERDIR | ZEND_INI_SYSTEM
+const INI_ALL: u8 = 7;
+
+/// Register ini settings with their default values
+pub(crate) fn register_ini_entries(entries: &[(String, String)], module_number: c_int) {
+    // zend copies the names and values while registering
+    let strings: Vec<(CString, CString)> = entries
+        .iter()
+        .map(|(name, value)| {
+            (
+                CString::new(name.as_str()).unwrap(),
+                CString::new(value.as_str()).unwrap(),
+            )
+        })
+        .collect();
+    let mut definitions: Vec<zend_ini_entry_def> = strings
+        .iter()
+        .map(|(name, value)| ini_entry_def(name.as_ptr(), value.as_ptr()))
+        .collect();
+    definitions.push(ini_entry_def(ptr::null(), ptr::null()));
+
+    unsafe {
+        zend_register_ini_entries(definitions.as_ptr(), module_number);
+    }
+}
+
+fn ini_entry_def(name: *const c_char, value: *const c_char) -> zend_ini_entry_def {
+    let (name_length, value_length) = if name.is_null() {
+        (0, 0)
+    } else {
+        unsafe { (CStr::from_ptr(name).to_bytes().len(), CStr::from_ptr(value).to_bytes().len()) }
+    };
+    zend_ini_entry_def {
+        name,
+        on_modify: None,
+        mh_arg1: ptr::null_mut(),
+        mh_arg2: ptr::null_mut(),
+        mh_arg3: ptr::null_mut(),
+        value,
+        displayer: None,
+        value_length: value_length as u32,
+        name_length: name_length as u16,
+        modifiable: INI_ALL,
+    }
+}
+
+pub(crate) fn unregister_ini_entries(module_number: c_int) {
+    unsafe {
+        zend_unregister_ini_entries(module_number);
+    }
+}
+
+/// Get the current value of an ini setting
+pub(crate) fn ini_string(name: &str) -> Option<String> {
+    let name = CString::new(name).ok()?;
+    let mut exists = 0;
+    let value = unsafe {
+        zend_ini_string_ex(
+            name.as_ptr() as *mut c_char,
+            name.as_bytes().len(),
+            0,
+            &mut exists,
+        )
+    };
+    if exists == 0 || value.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned())
+    }
+}
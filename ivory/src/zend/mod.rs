@@ -1,9 +1,22 @@
+pub use self::array::{ZArray, ZArrayIter};
+pub use self::constant::{register_constant, register_constants, PhpConstants};
+pub use self::enums::{register_enum, PhpEnum};
 pub use self::function::*;
+pub use self::hash_table::ZHashTable;
 pub use self::module::*;
-pub use self::zval::{ExecuteData, ZVal, ZValType};
+pub use self::resource::{register_resource, Resource};
+pub use self::zval::{ArgIterator, ExecuteData, GetTypeHint, IntoZVal, ZVal, ZValType};
 
 mod array;
+mod constant;
+mod enums;
 mod function;
+mod hash_table;
+// ini settings are only registered by optional features
+#[cfg_attr(not(feature = "log"), allow(dead_code))]
+pub(crate) mod ini;
 mod module;
+mod object;
+mod resource;
 mod string;
 mod zval;
@@ -2,6 +2,8 @@ use std;
 use std::mem;
 use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_ushort, c_void};
 
+use ivory_sys::ZEND_MODULE_API_NO;
+
 use crate::zend::function::{ArgInfo, Function};
 
 pub(crate) type StartupFunc = extern "C" fn(type_: c_int, module_number: c_int) -> c_int;
@@ -45,10 +47,9 @@ pub struct ModuleInternal {
 
 impl ModuleInternal {
     pub fn new(name: *const c_char, version: *const c_char) -> ModuleInternal {
-        #[allow(clippy::inconsistent_digit_grouping)]
         ModuleInternal {
             size: mem::size_of::<ModuleInternal>() as u16,
-            zend_api: 2018_07_31,
+            zend_api: ZEND_MODULE_API_NO,
             zend_debug: 0,
             zts: 0,
             ini_entry: std::ptr::null(),
@@ -70,7 +71,8 @@ impl ModuleInternal {
             type_: 0,
             handle: std::ptr::null(),
             module_number: 0,
-            build_id: c_str!("API20180731,NTS"),
+            // set by the build script from the module api of the php headers
+            build_id: c_str!(env!("IVORY_BUILD_ID")),
         }
     }
 
@@ -108,6 +110,19 @@ impl FunctionMeta {
     }
 }
 
+/// Module initialization done by ivory itself, called from the module startup generated by `ivory_module!`
+pub fn startup_module(name: *const c_char, module_number: c_int) {
+    #[cfg(feature = "log")]
+    crate::logger::register(name, module_number);
+    #[cfg(not(feature = "log"))]
+    let _ = (name, module_number);
+}
+
+/// Module cleanup done by ivory itself, called from the module shutdown generated by `ivory_module!`
+pub fn shutdown_module(module_number: c_int) {
+    crate::zend::ini::unregister_ini_entries(module_number);
+}
+
 pub struct PhpModule {
     pub name: *const c_char,
     pub version: *const c_char,
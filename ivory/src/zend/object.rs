@@ -0,0 +1,62 @@
+use std::mem;
+use std::os::raw::c_char;
+
+use indexmap::IndexMap;
+use ivory_sys::*;
+
+use crate::zend::array::parse_zend_array;
+use crate::zend::ZVal;
+use crate::{ArrayKey, PhpVal};
+
+// gc flag php uses to detect recursion while walking nested values
+const GC_PROTECTED: u32 = 1 << 5;
+
+/// Read the public properties of an object in declaration order, `None` if the object contains itself
+///
+/// Like `get_object_vars` called from outside the class, private and protected properties are skipped.
+pub(super) unsafe fn parse_zend_object(obj: *mut zend_object) -> Option<IndexMap<String, PhpVal>> {
+    if (*obj).gc.u.type_info & GC_PROTECTED != 0 {
+        return None;
+    }
+    (*obj).gc.u.type_info |= GC_PROTECTED;
+    // declared properties are only added to the property table once it is needed
+    rebuild_object_properties(obj);
+    let properties = parse_zend_array(*(*obj).properties);
+    (*obj).gc.u.type_info &= !GC_PROTECTED;
+
+    Some(
+        properties
+            .into_iter()
+            .filter_map(|(key, value)| public_property_name(key).map(|name| (name, value)))
+            .collect(),
+    )
+}
+
+/// The name of a property, `None` for private and protected properties
+fn public_property_name(key: ArrayKey) -> Option<String> {
+    match key {
+        ArrayKey::Int(index) => Some(index.to_string()),
+        // php mangles the names of private and protected properties as "\0Class\0name" or "\0*\0name"
+        ArrayKey::String(ref name) if name.starts_with('\0') => None,
+        ArrayKey::String(name) => Some(name),
+    }
+}
+
+/// Create a `stdClass` object with the properties
+pub(super) fn create_zend_object(properties: IndexMap<String, PhpVal>) -> zval {
+    unsafe {
+        let mut object: zval = mem::zeroed();
+        object_init(&mut object);
+        for (name, value) in properties {
+            // the property takes its own reference, ours is released afterwards
+            let mut value = ZVal::from(value);
+            add_property_zval_ex(
+                &mut object,
+                name.as_ptr() as *const c_char,
+                name.len(),
+                value.as_mut_ptr(),
+            );
+        }
+        object
+    }
+}
@@ -0,0 +1,129 @@
+use lazy_static::lazy_static;
+use std::any::{type_name, TypeId};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::os::raw::{c_int, c_void};
+use std::sync::Mutex;
+
+use ivory_sys::*;
+
+use crate::args::FromArg;
+use crate::zend::{IntoZVal, ZVal, ZValType};
+use crate::error::UnregisteredResourceError;
+use crate::CastError;
+
+struct ResourceType {
+    id: c_int,
+    // the name needs to be kept alive for as long as the resource type is registered
+    _name: CString,
+}
+
+lazy_static! {
+    static ref RESOURCE_TYPES: Mutex<HashMap<TypeId, ResourceType>> = Mutex::new(HashMap::new());
+}
+
+unsafe extern "C" fn destroy_resource<T>(res: *mut zend_resource) {
+    let ptr = (*res).ptr as *mut T;
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+        (*res).ptr = std::ptr::null_mut();
+    }
+}
+
+/// Register `T` as resource type, this is done automatically for every resource type used by exported functions
+pub fn register_resource<T: 'static>(module_number: c_int) {
+    let full_name = type_name::<T>();
+    let short_name = full_name.rsplit("::").next().unwrap_or(full_name);
+    let name = CString::new(short_name).unwrap();
+    let id = unsafe {
+        zend_register_list_destructors_ex(
+            Some(destroy_resource::<T>),
+            None,
+            name.as_ptr(),
+            module_number,
+        )
+    };
+    RESOURCE_TYPES
+        .lock()
+        .unwrap()
+        .insert(TypeId::of::<T>(), ResourceType { id, _name: name });
+}
+
+fn resource_type_id<T: 'static>() -> Option<c_int> {
+    RESOURCE_TYPES
+        .lock()
+        .unwrap()
+        .get(&TypeId::of::<T>())
+        .map(|ty| ty.id)
+}
+
+/// A php resource owning a rust value
+///
+/// The rust value is dropped once php frees the resource.
+/// Exported functions can return a `Resource<T>` and take it back as `&Resource<T>` argument.
+pub struct Resource<T: 'static> {
+    zval: ZVal,
+    _marker: PhantomData<T>,
+}
+
+impl<T: 'static> Resource<T> {
+    /// Hand a value over to php
+    ///
+    /// Fails if `T` isn't registered, which only happens when no exported function uses `Resource<T>`
+    /// or the type isn't in scope of the `ivory_module!` call.
+    pub fn new(value: T) -> Result<Self, UnregisteredResourceError> {
+        let id = resource_type_id::<T>().ok_or_else(|| UnregisteredResourceError(type_name::<T>()))?;
+        let ptr = Box::into_raw(Box::new(value)) as *mut c_void;
+        let res = unsafe { zend_register_resource(ptr, id) };
+        Ok(Resource {
+            zval: ZVal::from_resource(res),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: 'static> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        Resource {
+            zval: self.zval.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> Deref for Resource<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*((*self.zval.resource().unwrap()).ptr as *const T) }
+    }
+}
+
+impl<T: 'static> FromArg for Resource<T> {
+    fn from_arg(arg: Option<&ZVal>, _strict: bool) -> Result<Self, CastError> {
+        let zval = arg.map(ZVal::deref_indirect);
+        let res = zval.and_then(ZVal::resource);
+        match (zval, res) {
+            (Some(zval), Some(res))
+                // the resource might already be closed
+                if Some(unsafe { (*res).type_ }) == resource_type_id::<T>()
+                    && !unsafe { (*res).ptr }.is_null() =>
+            {
+                Ok(Resource {
+                    zval: zval.clone(),
+                    _marker: PhantomData,
+                })
+            }
+            (Some(zval), _) => Err(CastError::new(zval.get_type())),
+            (None, _) => Err(CastError::new(ZValType::Undef)),
+        }
+    }
+}
+
+impl<T: 'static> IntoZVal for Resource<T> {
+    fn into_zval(self) -> ZVal {
+        self.zval
+    }
+}
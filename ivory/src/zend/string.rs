@@ -1,16 +1,22 @@
 use ivory_sys::*;
-use std::alloc::{alloc, Layout};
 use std::mem::size_of;
 use std::ptr;
 use std::str;
 
+use crate::zend::zval::IS_PHP8;
+
 pub(super) unsafe fn parse_zend_string(string: *const zend_string) -> String {
+    borrow_zend_string(string).to_string()
+}
+
+/// Borrow the content of a zend string, which lives as long as the string isn't released
+pub(super) unsafe fn borrow_zend_string<'a>(string: *const zend_string) -> &'a str {
     let len = (*string).len;
     let base = string as *const u8;
     let str_start = base.add(size_of::<ZendStringHeader>());
 
     let slice: &[u8] = std::slice::from_raw_parts(str_start, len);
-    str::from_utf8_unchecked(slice).to_string()
+    str::from_utf8_unchecked(slice)
 }
 
 #[repr(C)]
@@ -20,27 +26,32 @@ pub struct ZendStringHeader {
     len: usize,
 }
 
+// gc type info of non-persistent strings, php 8 flags them as not collectable
+const GC_STRING: u32 = if IS_PHP8 { 6 | (1 << 4) } else { 6 };
+
+/// Allocate a zend string through the engine's memory manager, the same as `zend_string_init`
+///
+/// The string starts with a refcount of 1 and is released by the engine like any other string.
 pub(super) fn construct_zend_string(string: String) -> *mut zend_string {
     let len = string.len();
 
     let header_size = size_of::<ZendStringHeader>();
-
-    let layout = Layout::from_size_align(len + header_size, size_of::<zend_string>())
-        .expect("invalid layout");
-    let raw = unsafe { alloc(layout) };
+    // zend strings are null terminated and their size is aligned to 8 bytes
+    let size = (header_size + len + 1 + 7) & !7;
 
     let header = ZendStringHeader {
         gc: zend_refcounted_h {
-            refcount: 1, // ?? no clue actually,
-            u: _zend_refcounted_h__bindgen_ty_1 { type_info: 0 },
+            refcount: 1,
+            u: _zend_refcounted_h__bindgen_ty_1 { type_info: GC_STRING },
         },
         h: 0,
         len,
     };
     unsafe {
+        let raw = _emalloc(size) as *mut u8;
         ptr::write(raw as *mut ZendStringHeader, header);
-        ptr::copy(string.as_ptr(), raw.add(header_size), len);
-    };
-
-    raw as *mut zend_string
+        ptr::copy_nonoverlapping(string.as_ptr(), raw.add(header_size), len);
+        ptr::write(raw.add(header_size + len), 0);
+        raw as *mut zend_string
+    }
 }
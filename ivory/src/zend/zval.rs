@@ -1,13 +1,18 @@
 use std::fmt;
 use std::fmt::Display;
-use std::intrinsics::transmute;
-use std::mem::size_of;
+use std::convert::TryFrom;
+use std::mem::{size_of, ManuallyDrop};
+use std::os::raw::c_char;
 
 use ivory_sys::*;
 
 use crate::zend::array::parse_zend_array;
+use crate::zend::enums::{enum_case_zval, parse_enum_case};
+use crate::zend::object::{create_zend_object, parse_zend_object};
 use crate::zend::string::{construct_zend_string, parse_zend_string};
-use crate::{ArrayKey, PhpVal};
+use crate::error::UnknownTypeError;
+use crate::externs::throw_error;
+use crate::{ArrayKey, CastError, FromPhpVal, IntoPhpVal, PhpVal};
 
 #[repr(transparent)]
 pub struct ExecuteData(zend_execute_data);
@@ -23,53 +28,208 @@ impl ExecuteData {
         unsafe { self_ptr.add(offset) }
     }
 
-    pub unsafe fn get_arg(&self, i: u32) -> &ZVal {
+    /// Get an argument without checking if it was passed
+    pub unsafe fn arg_unchecked(&self, i: u32) -> &ZVal {
         let base = self.get_arg_base();
-        let val_ptr = base.add(i as usize);
-        &*val_ptr
+        &*base.add(i as usize)
     }
 
-    pub fn args(&self) -> IntoArgIterator {
-        IntoArgIterator {
-            base: self.get_arg_base(),
+    /// Get an argument or `None` if less than `i + 1` arguments are passed
+    pub fn arg(&self, i: u32) -> Option<&ZVal> {
+        if i < self.num_args() {
+            Some(unsafe { self.arg_unchecked(i) })
+        } else {
+            None
+        }
+    }
+
+    /// Whether the calling code declared `strict_types=1`
+    pub fn is_strict_types(&self) -> bool {
+        let caller = self.0.prev_execute_data;
+        if caller.is_null() || unsafe { (*caller).func }.is_null() {
+            return false;
+        }
+        unsafe { (*(*caller).func).common.fn_flags & ZEND_ACC_STRICT_TYPES != 0 }
+    }
+
+    pub fn args(&self) -> ArgIterator<'_> {
+        ArgIterator {
+            data: self,
             count: self.num_args(),
             item: 0,
         }
     }
 }
 
-pub struct IntoArgIterator {
-    base: *const ZVal,
+pub struct ArgIterator<'a> {
+    data: &'a ExecuteData,
     count: u32,
     item: u32,
 }
 
-impl Iterator for IntoArgIterator {
-    type Item = PhpVal;
+impl<'a> Iterator for ArgIterator<'a> {
+    type Item = &'a ZVal;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.item < self.count {
-            let val = unsafe { (*self.base.add(self.item as usize)).as_php_val() };
+            let val = unsafe { self.data.arg_unchecked(self.item) };
             self.item += 1;
             Some(val)
         } else {
-            Some(PhpVal::Undef)
+            None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.item) as usize;
+        (remaining, Some(remaining))
+    }
 }
 
+impl<'a> ExactSizeIterator for ArgIterator<'a> {}
+
+// type flags stored in the second byte of the zval type info
+pub(super) const IS_TYPE_REFCOUNTED: u8 = 1 << 0;
+pub(super) const IS_TYPE_COLLECTABLE: u8 = 1 << 1;
+const ZEND_ACC_STRICT_TYPES: u32 = 1 << 31;
+// gc flag of interned strings and immutable arrays
+pub(super) const GC_IMMUTABLE: u32 = 1 << 6;
+
+/// An owned php value
+///
+/// Cloning a `ZVal` increments the refcount of the underlying value and dropping it releases it
+/// through `zval_ptr_dtor`, the same as `Z_TRY_ADDREF` and `zval_ptr_dtor` do for php's own code.
+///
+/// Values owned by the engine, like function arguments, are exposed as a borrowed `&ZVal` which
+/// doesn't touch the refcount.
+///
+/// Since php's refcounting and memory manager are not thread safe, `ZVal` is neither `Send` nor `Sync`,
+/// convert it into a `PhpVal` to move a value to a different thread.
 #[repr(transparent)]
 pub struct ZVal(zval);
 
+/// Take ownership of a raw zval, the zval will be released once the `ZVal` is dropped
 impl From<zval> for ZVal {
     fn from(val: zval) -> Self {
         ZVal(val)
     }
 }
 
+impl Clone for ZVal {
+    fn clone(&self) -> Self {
+        let copy = ZVal(self.0);
+        if copy.is_refcounted() {
+            unsafe {
+                (*copy.0.value.counted).gc.refcount += 1;
+            }
+        }
+        copy
+    }
+}
+
+impl Drop for ZVal {
+    fn drop(&mut self) {
+        unsafe {
+            zval_ptr_dtor(&mut self.0);
+        }
+    }
+}
+
+impl Default for ZVal {
+    fn default() -> Self {
+        PhpVal::Undef.into()
+    }
+}
+
 impl ZVal {
+    /// Borrow a zval owned by the engine without taking ownership
+    pub unsafe fn from_raw<'a>(val: *const zval) -> &'a ZVal {
+        &*(val as *const ZVal)
+    }
+
+    /// Hand ownership of the value over to the caller
+    pub fn into_raw(self) -> zval {
+        let val = self.0;
+        std::mem::forget(self);
+        val
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut zval {
+        &mut self.0
+    }
+
+    /// Get a pointer to the referenced value if this zval is a reference
+    pub(crate) fn reference_target(&self) -> Option<*mut zval> {
+        if self.get_type() == ZValType::Reference {
+            Some(unsafe { &mut (*self.0.value.ref_).val as *mut zval })
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn from_resource(res: *mut zend_resource) -> ZVal {
+        ZVal(zval {
+            value: zend_value { res },
+            u1: _zval_struct__bindgen_ty_1 {
+                v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
+                    type_: ZValType::Resource.code(),
+                    type_flags: IS_TYPE_REFCOUNTED,
+                    u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
+                },
+            },
+            u2: _zval_struct__bindgen_ty_2 { extra: 0 },
+        })
+    }
+
+    /// Add a reference to an object owned by the engine
+    #[cfg(php81)]
+    pub(super) unsafe fn from_object(obj: *mut zend_object) -> ZVal {
+        (*obj).gc.refcount += 1;
+        ZVal(zval {
+            value: zend_value { obj },
+            u1: _zval_struct__bindgen_ty_1 {
+                v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
+                    type_: ZValType::Object.code(),
+                    type_flags: IS_TYPE_REFCOUNTED | IS_TYPE_COLLECTABLE,
+                    u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
+                },
+            },
+            u2: _zval_struct__bindgen_ty_2 { extra: 0 },
+        })
+    }
+
+    pub(crate) fn resource(&self) -> Option<*mut zend_resource> {
+        if self.get_type() == ZValType::Resource {
+            Some(unsafe { self.0.value.res })
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn array(&self) -> Option<*mut zend_array> {
+        if self.get_type() == ZValType::Array {
+            Some(unsafe { self.0.value.arr })
+        } else {
+            None
+        }
+    }
+
     pub fn get_type(&self) -> ZValType {
-        unsafe { self.0.u1.v.type_.into() }
+        let code = unsafe { self.0.u1.v.type_ };
+        ZValType::try_from(code).unwrap_or(ZValType::Unknown(code))
+    }
+
+    pub fn is_refcounted(&self) -> bool {
+        unsafe { self.0.u1.v.type_flags & IS_TYPE_REFCOUNTED != 0 }
+    }
+
+    /// The refcount of the underlying value or `None` if the value isn't refcounted
+    pub fn refcount(&self) -> Option<u32> {
+        if self.is_refcounted() {
+            Some(unsafe { (*self.0.value.counted).gc.refcount })
+        } else {
+            None
+        }
     }
 
     pub unsafe fn as_i64(&self) -> i64 {
@@ -88,48 +248,182 @@ impl ZVal {
         parse_zend_array(*self.0.value.arr)
     }
 
+    /// Follow indirect zvals as used by symbol tables and property tables
+    pub fn deref_indirect(&self) -> &ZVal {
+        if unsafe { self.0.u1.v.type_ } == ZValType::Indirect.code() {
+            unsafe { ZVal::from_raw(self.0.value.zv) }.deref_indirect()
+        } else {
+            self
+        }
+    }
+
     pub fn as_php_val(&self) -> PhpVal {
-        match self.get_type() {
+        let val = self.deref_indirect();
+        match val.get_type() {
             ZValType::Undef => PhpVal::Undef,
             ZValType::Null => PhpVal::Null,
             ZValType::False => PhpVal::Bool(false),
             ZValType::True => PhpVal::Bool(true),
-            ZValType::Long => PhpVal::Long(unsafe { self.as_i64() }),
-            ZValType::Double => PhpVal::Double(unsafe { self.as_f64() }),
-            ZValType::String => PhpVal::String(unsafe { self.as_str() }),
-            ZValType::Array => PhpVal::Array(unsafe { self.as_array() }),
+            ZValType::Long => PhpVal::Long(unsafe { val.as_i64() }),
+            ZValType::Double => PhpVal::Double(unsafe { val.as_f64() }),
+            ZValType::String => PhpVal::String(unsafe { val.as_str() }),
+            ZValType::Array => PhpVal::Array(unsafe { val.as_array() }),
+            ZValType::Object => match unsafe { parse_enum_case(val.0.value.obj) } {
+                Some(case) => case,
+                // an object nested inside itself is converted to null, like php's var_export does
+                None => match unsafe { parse_zend_object(val.0.value.obj) } {
+                    Some(properties) => PhpVal::Object(properties),
+                    None => PhpVal::Null,
+                },
+            },
+            ZValType::Resource => PhpVal::Resource(unsafe { (*val.0.value.res).handle } as u64),
+            ZValType::Reference => {
+                let target = val.reference_target().unwrap();
+                PhpVal::Reference(Box::new(unsafe { ZVal::from_raw(target) }.as_php_val()))
+            }
             _ => PhpVal::Undef,
         }
     }
+
+    /// Convert the value into the argument type `T`
+    ///
+    /// Unless `strict` is set the value is converted using php's coercive typing rules,
+    /// which also accepts objects implementing `__toString` for string arguments.
+    pub fn cast<T: FromPhpVal>(&self, strict: bool) -> Result<T, CastError> {
+        if strict {
+            return T::from_php_val(self.as_php_val());
+        }
+        if T::TYPE_HINT == ZValType::String {
+            if let Some(string) = self.object_to_string() {
+                return T::coerce_php_val(string.as_php_val());
+            }
+        }
+        T::coerce_php_val(self.as_php_val())
+    }
+
+    /// Call `__toString` if the value is an object implementing it
+    fn object_to_string(&self) -> Option<ZVal> {
+        let val = self.deref_indirect();
+        let val = match val.reference_target() {
+            Some(target) => unsafe { ZVal::from_raw(target) },
+            None => val,
+        };
+        if val.get_type() != ZValType::Object {
+            return None;
+        }
+        unsafe {
+            if (*(*val.0.value.obj).ce).__tostring.is_null() {
+                return None;
+            }
+            let string = zval_get_string_func(&val.0 as *const zval as *mut zval);
+            Some(ZVal::from_zend_string(string))
+        }
+    }
+
+    /// Take ownership of a string returned by the engine
+    pub(super) fn from_zend_string(string: *mut zend_string) -> ZVal {
+        // interned strings aren't refcounted and must not be released
+        let interned = unsafe { (*string).gc.u.type_info } & GC_IMMUTABLE != 0;
+        ZVal(zval {
+            value: zend_value { str: string },
+            u1: _zval_struct__bindgen_ty_1 {
+                v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
+                    type_: ZValType::String.code(),
+                    type_flags: if interned { 0 } else { IS_TYPE_REFCOUNTED },
+                    u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
+                },
+            },
+            u2: _zval_struct__bindgen_ty_2 { extra: 0 },
+        })
+    }
 }
 
+/// The type of a zval
+///
+/// The numeric type codes differ between php 7 and php 8, conversion from and to the type codes
+/// uses the codes of the php version ivory is build against.
 #[derive(Debug, PartialEq, Clone, Copy)]
-#[repr(u8)]
 pub enum ZValType {
-    Undef = 0,
-    Null = 1,
-    False = 2,
-    True = 3,
-    Long = 4,
-    Double = 5,
-    String = 6,
-    Array = 7,
-    Object = 8,
-    Resource = 9,
-    Reference = 10,
+    Undef,
+    Null,
+    False,
+    True,
+    Long,
+    Double,
+    String,
+    Array,
+    Object,
+    Resource,
+    Reference,
 
     // internal types
-    ConstantAST = 11,
-    Indirect = 13,
-    Ptr = 14,
-    Err = 15,
+    ConstantAST,
+    Indirect,
+    Ptr,
+    /// php 8 only
+    AliasPtr,
+    Err,
 
     // fake types for type hinting
-    Bool = 16,
-    Callable = 17,
-    Iterable = 18,
-    Void = 19,
-    Number = 20,
+    Bool,
+    Callable,
+    Iterable,
+    Void,
+    Number,
+    /// php 8 only
+    Static,
+    /// php 8 only
+    Mixed,
+    /// php 8.1 only
+    Never,
+
+    /// A type code unknown to ivory
+    Unknown(u8),
+}
+
+pub(super) const IS_PHP8: bool = PHP_VERSION_ID >= 80000;
+const IS_PHP81: bool = PHP_VERSION_ID >= 80100;
+
+impl ZValType {
+    /// Get the type code used by the engine
+    pub const fn code(self) -> u8 {
+        match self {
+            ZValType::Undef => 0,
+            ZValType::Null => 1,
+            ZValType::False => 2,
+            ZValType::True => 3,
+            ZValType::Long => 4,
+            ZValType::Double => 5,
+            ZValType::String => 6,
+            ZValType::Array => 7,
+            ZValType::Object => 8,
+            ZValType::Resource => 9,
+            ZValType::Reference => 10,
+            ZValType::ConstantAST => 11,
+            ZValType::Indirect if IS_PHP8 => 12,
+            ZValType::Indirect => 13,
+            ZValType::Ptr if IS_PHP8 => 13,
+            ZValType::Ptr => 14,
+            ZValType::AliasPtr => 14,
+            ZValType::Err => 15,
+            ZValType::Bool if IS_PHP81 => 18,
+            ZValType::Bool if IS_PHP8 => 17,
+            ZValType::Bool => 16,
+            ZValType::Callable if IS_PHP8 => 12,
+            ZValType::Callable => 17,
+            ZValType::Iterable if IS_PHP8 => 13,
+            ZValType::Iterable => 18,
+            ZValType::Void if IS_PHP8 => 14,
+            ZValType::Void => 19,
+            ZValType::Number if IS_PHP81 => 19,
+            ZValType::Number if IS_PHP8 => 18,
+            ZValType::Number => 20,
+            ZValType::Static => 15,
+            ZValType::Mixed => 16,
+            ZValType::Never => 17,
+            ZValType::Unknown(code) => code,
+        }
+    }
 }
 
 impl Display for ZValType {
@@ -149,58 +443,77 @@ impl Display for ZValType {
             ZValType::ConstantAST => write!(f, "constant ast"),
             ZValType::Indirect => write!(f, "indirect"),
             ZValType::Ptr => write!(f, "pointer"),
+            ZValType::AliasPtr => write!(f, "alias pointer"),
             ZValType::Err => write!(f, "error"),
             ZValType::Bool => write!(f, "bool"),
             ZValType::Callable => write!(f, "callable"),
             ZValType::Iterable => write!(f, "iterable"),
             ZValType::Void => write!(f, "void"),
             ZValType::Number => write!(f, "number"),
+            ZValType::Static => write!(f, "static"),
+            ZValType::Mixed => write!(f, "mixed"),
+            ZValType::Never => write!(f, "never"),
+            ZValType::Unknown(code) => write!(f, "unknown type {}", code),
         }
     }
 }
 
+/// The type hint for arguments of a type, derived from its `FromPhpVal` implementation
 pub trait GetTypeHint {
     fn get_type_hint() -> ZValType;
-}
 
-macro_rules! impl_get_type_hint {
-    ($type:ty, $hint:expr) => {
-        impl GetTypeHint for $type {
-            #[inline]
-            fn get_type_hint() -> ZValType {
-                $hint
-            }
-        }
-    };
+    fn allow_null() -> bool {
+        false
+    }
 }
 
-impl_get_type_hint!(String, ZValType::String);
-impl_get_type_hint!(bool, ZValType::Bool);
-impl_get_type_hint!(f64, ZValType::Double);
-impl_get_type_hint!(f32, ZValType::Double);
-impl_get_type_hint!(u8, ZValType::Long);
-impl_get_type_hint!(u16, ZValType::Long);
-impl_get_type_hint!(u32, ZValType::Long);
-impl_get_type_hint!(u64, ZValType::Long);
-impl_get_type_hint!(usize, ZValType::Long);
-impl_get_type_hint!(i8, ZValType::Long);
-impl_get_type_hint!(i16, ZValType::Long);
-impl_get_type_hint!(i32, ZValType::Long);
-impl_get_type_hint!(i64, ZValType::Long);
-impl_get_type_hint!(isize, ZValType::Long);
+impl<T: FromPhpVal> GetTypeHint for T {
+    #[inline]
+    fn get_type_hint() -> ZValType {
+        T::TYPE_HINT
+    }
+
+    #[inline]
+    fn allow_null() -> bool {
+        T::ALLOW_NULL
+    }
+}
 
 impl From<ZValType> for u8 {
     fn from(val: ZValType) -> Self {
-        unsafe { transmute(val) }
+        val.code()
     }
 }
 
-impl From<u8> for ZValType {
-    fn from(val: u8) -> Self {
-        if val > 10 {
-            panic!("invalid zval type");
-        }
-        unsafe { transmute(val) }
+/// Convert the type code of a zval
+///
+/// Only the types that can be stored in a zval are converted, the type codes used for type hinting
+/// overlap with internal types on php 8.
+impl TryFrom<u8> for ZValType {
+    type Error = UnknownTypeError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        Ok(match code {
+            0 => ZValType::Undef,
+            1 => ZValType::Null,
+            2 => ZValType::False,
+            3 => ZValType::True,
+            4 => ZValType::Long,
+            5 => ZValType::Double,
+            6 => ZValType::String,
+            7 => ZValType::Array,
+            8 => ZValType::Object,
+            9 => ZValType::Resource,
+            10 => ZValType::Reference,
+            11 => ZValType::ConstantAST,
+            12 if IS_PHP8 => ZValType::Indirect,
+            13 if IS_PHP8 => ZValType::Ptr,
+            14 if IS_PHP8 => ZValType::AliasPtr,
+            13 => ZValType::Indirect,
+            14 => ZValType::Ptr,
+            15 => ZValType::Err,
+            _ => return Err(UnknownTypeError(code)),
+        })
     }
 }
 
@@ -208,7 +521,7 @@ impl From<ZValType> for _zval_struct__bindgen_ty_1 {
     fn from(ty: ZValType) -> Self {
         _zval_struct__bindgen_ty_1 {
             v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
-                type_: ty as zend_uchar,
+                type_: ty.code(),
                 type_flags: 0,
                 u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
             },
@@ -216,6 +529,36 @@ impl From<ZValType> for _zval_struct__bindgen_ty_1 {
     }
 }
 
+/// Conversion of return values into a zval
+pub trait IntoZVal {
+    fn into_zval(self) -> ZVal;
+}
+
+impl<T: IntoPhpVal> IntoZVal for T {
+    fn into_zval(self) -> ZVal {
+        ZVal::from(self.into_php_val())
+    }
+}
+
+/// Errors are thrown as an `Error` exception, the function returns `null`
+impl<T: IntoZVal, E: Display> IntoZVal for Result<T, E> {
+    fn into_zval(self) -> ZVal {
+        match self {
+            Ok(value) => value.into_zval(),
+            Err(err) => {
+                crate::externs::throw_error(format!("{}", err));
+                PhpVal::Null.into()
+            }
+        }
+    }
+}
+
+impl IntoZVal for ZVal {
+    fn into_zval(self) -> ZVal {
+        self
+    }
+}
+
 impl From<PhpVal> for ZVal {
     fn from(input: PhpVal) -> Self {
         let ty = input.get_type();
@@ -244,13 +587,11 @@ impl From<PhpVal> for ZVal {
                 value: zend_value { lval: 0 },
                 u1: _zval_struct__bindgen_ty_1 {
                     v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
-                        type_: {
-                            if val {
-                                ZValType::True
-                            } else {
-                                ZValType::False
-                            }
-                        } as zend_uchar,
+                        type_: if val {
+                            ZValType::True.code()
+                        } else {
+                            ZValType::False.code()
+                        },
                         type_flags: 0,
                         u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
                     },
@@ -261,7 +602,13 @@ impl From<PhpVal> for ZVal {
                 value: zend_value {
                     str: construct_zend_string(val),
                 },
-                u1: ty.into(),
+                u1: _zval_struct__bindgen_ty_1 {
+                    v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
+                        type_: ty.code(),
+                        type_flags: IS_TYPE_REFCOUNTED,
+                        u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
+                    },
+                },
                 u2: _zval_struct__bindgen_ty_2 { extra: 0 },
             }),
             PhpVal::Array(vec) => {
@@ -271,22 +618,28 @@ impl From<PhpVal> for ZVal {
                     let map: *mut _zend_array = _zend_new_array(vec.len() as u32);
                     let mut arr = zval {
                         value: zend_value { arr: map },
-                        u1: ty.into(),
+                        u1: _zval_struct__bindgen_ty_1 {
+                            v: _zval_struct__bindgen_ty_1__bindgen_ty_1 {
+                                type_: ty.code(),
+                                type_flags: IS_TYPE_REFCOUNTED | IS_TYPE_COLLECTABLE,
+                                u: _zval_struct__bindgen_ty_1__bindgen_ty_1__bindgen_ty_1 { extra: 0 },
+                            },
+                        },
                         u2: _zval_struct__bindgen_ty_2 { extra: 0 },
                     };
                     let arr_ptr: *mut zval = &mut arr;
                     for (key, val) in vec.into_iter() {
-                        let val_ptr: *mut zval =
-                            Box::into_raw(Box::new(ZVal::from(val))) as *mut zval;
-                        match key {
+                        // the array takes ownership of the value
+                        let mut val = ManuallyDrop::new(ZVal::from(val));
+                        let val_ptr: *mut zval = &mut val.0;
+                        match key.normalize() {
                             ArrayKey::Int(index) => {
-                                add_index_zval(arr_ptr, index, val_ptr);
+                                add_index_zval(arr_ptr, index as zend_ulong, val_ptr);
                             }
                             ArrayKey::String(key) => {
-                                let key_len = key.len();
-                                let bytes =
-                                    Box::into_raw(key.into_bytes().into_boxed_slice()) as *const i8;
-                                add_assoc_zval_ex(arr_ptr, bytes, key_len, val_ptr);
+                                // the key is copied by zend
+                                let key_ptr = key.as_ptr() as *const c_char;
+                                add_assoc_zval_ex(arr_ptr, key_ptr, key.len(), val_ptr);
                             }
                         }
                     }
@@ -294,7 +647,32 @@ impl From<PhpVal> for ZVal {
                     ZVal(arr)
                 }
             }
-            _ => unimplemented!(),
+            // a reference that isn't shared with anything else behaves the same as a plain value
+            PhpVal::Reference(inner) => ZVal::from(*inner),
+            PhpVal::Object(properties) => ZVal(create_zend_object(properties)),
+            PhpVal::EnumCase { class, case } => enum_case_zval(&class, &case),
+            PhpVal::Resource(handle) => match find_resource(handle) {
+                Some(resource) => resource,
+                None => {
+                    throw_error(format!("Resource #{} doesn't exist", handle));
+                    PhpVal::Null.into()
+                }
+            },
+        }
+    }
+}
+
+/// Look up a resource by its handle, adding a reference to it
+///
+/// Resources can't be created from a handle, so only resources that are still alive can be converted back.
+fn find_resource(handle: u64) -> Option<ZVal> {
+    unsafe {
+        let list = std::ptr::addr_of!(executor_globals.regular_list);
+        let val = zend_hash_index_find(list, handle as zend_ulong);
+        if val.is_null() {
+            None
+        } else {
+            Some(ZVal::from_raw(val).clone())
         }
     }
 }
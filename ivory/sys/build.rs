@@ -4,6 +4,7 @@ extern crate num_cpus;
 
 use bindgen::Builder;
 use std::env;
+use std::fs;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
@@ -53,6 +54,26 @@ fn exists(path: &str) -> bool {
     Path::new(target(path).as_str()).exists()
 }
 
+/// Read the value of a numeric `#define` from a php header
+fn header_define(include_dir: &str, header: &str, name: &str) -> String {
+    let path = format!("{}/{}", include_dir, header);
+    let content =
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("#define"), Some(define), Some(value)) if define == name => {
+                    Some(value.to_string())
+                }
+                _ => None,
+            }
+        })
+        .next()
+        .unwrap_or_else(|| panic!("{} is not defined in {}", name, path))
+}
+
 fn compile_php(php_version: &str, link_static: bool) -> () {
     println_stderr!("Setting up PHP {}", php_version);
     run_command_or_fail("/".to_string(), "mkdir", &["-p", &target("")]);
@@ -161,17 +182,56 @@ fn main() {
         .rustfmt_bindings(true)
         .clang_args(includes)
         .whitelist_function("zend_error")
+        .whitelist_function("zend_type_error")
+        .whitelist_function("zend_throw_error")
         .whitelist_function("php_info_print_table_start")
         .whitelist_function("php_info_print_table_row")
         .whitelist_function("php_info_print_table_end")
         .whitelist_function("php_printf")
+        .whitelist_function("php_output_write")
+        .whitelist_function("_emalloc")
         .whitelist_function("_zend_new_array")
         .whitelist_function("add_index_zval")
         .whitelist_function("add_assoc_zval_ex")
+        .whitelist_function("zend_array_dup")
+        .whitelist_function("zend_hash_index_find")
+        .whitelist_function("zend_hash_str_find")
+        .whitelist_function("zend_hash_index_add")
+        .whitelist_function("zend_hash_str_add")
+        .whitelist_function("zend_hash_index_update")
+        .whitelist_function("zend_hash_str_update")
+        .whitelist_function("zend_hash_index_del")
+        .whitelist_function("zend_hash_str_del")
+        .whitelist_function("zend_hash_next_index_insert")
+        .whitelist_function("zend_hash_sort_ex")
+        .whitelist_function("zend_hash_clean")
+        .whitelist_function("object_init")
+        .whitelist_function("rebuild_object_properties")
+        .whitelist_function("add_property_zval_ex")
         .whitelist_function("zval_ptr_dtor")
+        .whitelist_function("zval_get_string_func")
+        .whitelist_function("zend_register_list_destructors_ex")
+        .whitelist_function("zend_register_resource")
+        .whitelist_function("zend_register_ini_entries")
+        .whitelist_function("zend_unregister_ini_entries")
+        .whitelist_function("zend_ini_string_ex")
+        .whitelist_function("php_log_err_with_severity")
+        .whitelist_function("zend_register_long_constant")
+        .whitelist_function("zend_register_stringl_constant")
+        // only declared by php 8 and 8.1, functions missing from the headers are left out of the bindings
+        .whitelist_function("zend_register_internal_enum")
+        .whitelist_function("zend_enum_add_case_cstr")
+        .whitelist_function("zend_enum_get_case_cstr")
+        .whitelist_function("zend_value_error")
+        .whitelist_var("PHP_VERSION_ID")
+        .whitelist_var("ZEND_MODULE_API_NO")
+        .whitelist_var("executor_globals")
+        .whitelist_var("zend_string_init_interned")
+        .whitelist_var("ZEND_ACC_ENUM")
         .whitelist_type("zval")
         .whitelist_type("zend_execute_data")
         .whitelist_type("zend_module_entry")
+        .whitelist_type("zend_class_constant")
         .derive_default(false)
         .header("wrapper.h")
         .generate()
@@ -181,4 +241,15 @@ fn main() {
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
+
+    // read by the build script of ivory as DEP_PHP7_VERSION_ID and DEP_PHP7_MODULE_API
+    // to enable the code for the php version the extension is built against
+    println!(
+        "cargo:version_id={}",
+        header_define(&include_dir, "main/php_version.h", "PHP_VERSION_ID")
+    );
+    println!(
+        "cargo:module_api={}",
+        header_define(&include_dir, "Zend/zend_modules.h", "ZEND_MODULE_API_NO")
+    );
 }
@@ -0,0 +1,163 @@
+use pretty_assertions::assert_eq;
+
+use ivory::{ArrayKey, ArrayKeyRef, FromPhpVal, IndexMap, PhpArray, PhpVal};
+
+fn key(key: &str) -> ArrayKey {
+    ArrayKey::String(key.to_string())
+}
+
+#[test]
+fn array_insert_get() {
+    let mut array = PhpArray::new();
+    assert_eq!(None, array.insert("foo", 1));
+    assert_eq!(None, array.insert(5, "bar".to_string()));
+    assert_eq!(Some(PhpVal::Long(1)), array.insert("foo", 2));
+    assert_eq!(2, array.len());
+    assert_eq!(Some(&PhpVal::Long(2)), array.get("foo"));
+    assert_eq!(Some(&PhpVal::String("bar".to_string())), array.get(5));
+    // numeric string keys are normalised
+    assert_eq!(Some(&PhpVal::String("bar".to_string())), array.get("5"));
+    assert_eq!(true, array.contains_key("5"));
+    assert_eq!(None, array.get("05"));
+
+    // replacing a value keeps its position
+    assert_eq!(vec![&key("foo"), &ArrayKey::Int(5)], array.keys().collect::<Vec<_>>());
+
+    if let Some(value) = array.get_mut("foo") {
+        *value = PhpVal::Null;
+    }
+    assert_eq!(Some(&PhpVal::Null), array.get("foo"));
+}
+
+#[test]
+fn array_push() {
+    let mut array = PhpArray::new();
+    assert_eq!(Some(0), array.push(1));
+    array.insert(5, 2);
+    assert_eq!(Some(6), array.push(3));
+    array.insert(-10, 4);
+    assert_eq!(Some(7), array.push(5));
+
+    // removing the last element doesn't free its index
+    array.remove(7);
+    assert_eq!(Some(8), array.push(6));
+
+    array.insert(i64::max_value(), 7);
+    assert_eq!(None, array.push(8));
+
+    array.clear();
+    assert_eq!(true, array.is_empty());
+    assert_eq!(Some(0), array.push(1));
+}
+
+#[test]
+fn array_remove_keeps_order() {
+    let mut array: PhpArray = vec![("a", 1), ("b", 2), ("c", 3)].into();
+    assert_eq!(Some(PhpVal::Long(2)), array.remove("b"));
+    assert_eq!(None, array.remove("b"));
+    assert_eq!(
+        vec![(key("a"), PhpVal::Long(1)), (key("c"), PhpVal::Long(3))],
+        Vec::<(ArrayKey, PhpVal)>::from(array)
+    );
+}
+
+#[test]
+fn array_eq() {
+    let array: PhpArray = vec![("a", 1), ("b", 2)].into();
+    assert_eq!(true, array == vec![("a", 1), ("b", 2)].into());
+    // the order matters, like php's ===
+    assert_eq!(false, array == vec![("b", 2), ("a", 1)].into());
+
+    // the next free index doesn't
+    let mut pushed = PhpArray::new();
+    pushed.push(1);
+    pushed.push(2);
+    pushed.remove(1);
+    assert_eq!(PhpArray::from(vec![1]), pushed);
+}
+
+#[test]
+fn array_is_list() {
+    assert_eq!(true, PhpArray::new().is_list());
+    assert_eq!(true, PhpArray::from(vec![1, 2, 3]).is_list());
+    let mut array = PhpArray::from(vec![1, 2, 3]);
+    array.remove(1);
+    assert_eq!(false, array.is_list());
+    let array: PhpArray = vec![(1, 2), (0, 1)].into();
+    assert_eq!(false, array.is_list());
+    let array: PhpArray = vec![("0", 1), ("1", 2)].into();
+    assert_eq!(true, array.is_list());
+}
+
+#[test]
+fn array_conversions() {
+    // duplicate keys are overwritten like php array literals
+    let array: PhpArray = vec![(key("a"), PhpVal::Long(1)), (key("a"), PhpVal::Long(2))].into();
+    assert_eq!(1, array.len());
+    assert_eq!(Some(&PhpVal::Long(2)), array.get("a"));
+
+    let mut map = IndexMap::new();
+    map.insert("b".to_string(), 1);
+    map.insert("a".to_string(), 2);
+    let array = PhpArray::from(map);
+    let map: IndexMap<ArrayKey, PhpVal> = array.clone().into();
+    assert_eq!(vec![&key("b"), &key("a")], map.keys().collect::<Vec<_>>());
+
+    let values: Vec<PhpVal> = array.clone().into();
+    assert_eq!(vec![PhpVal::Long(1), PhpVal::Long(2)], values);
+
+    let val: PhpVal = array.clone().into();
+    assert_eq!(
+        PhpVal::Array(vec![(key("b"), PhpVal::Long(1)), (key("a"), PhpVal::Long(2))]),
+        val
+    );
+    assert_eq!(array, PhpArray::from_php_val(val.clone()).unwrap());
+    assert_eq!(true, PhpArray::from_php_val(PhpVal::Long(1)).is_err());
+
+    let map = IndexMap::<String, i64>::from_php_val(val).unwrap();
+    assert_eq!(vec!["b", "a"], map.keys().collect::<Vec<_>>());
+
+    let collected: PhpArray = array.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+    assert_eq!(array, collected);
+    let mut count = 0;
+    for (_, value) in &array {
+        count += value.to_long();
+    }
+    assert_eq!(3, count);
+}
+
+#[test]
+fn array_normalizes_every_key() {
+    let mut array = PhpArray::new();
+    assert_eq!(None, array.insert(key("5"), 1));
+    assert_eq!(vec![&ArrayKey::Int(5)], array.keys().collect::<Vec<_>>());
+    assert_eq!(6, array.next_index());
+    assert_eq!(Some(&PhpVal::Long(1)), array.get(key("5")));
+    assert_eq!(true, array.contains_key(key("5")));
+    if let Some(value) = array.get_mut(key("5")) {
+        *value = PhpVal::Long(2);
+    }
+    assert_eq!(Some(PhpVal::Long(2)), array.insert(5, 3));
+    assert_eq!(Some(PhpVal::Long(3)), array.remove(key("5")));
+    assert_eq!(true, array.is_empty());
+
+    let array: PhpArray = vec![
+        (key("0"), PhpVal::Long(1)),
+        (ArrayKey::Int(0), PhpVal::Long(2)),
+        (key("01"), PhpVal::Long(3)),
+    ]
+    .into();
+    assert_eq!(vec![&ArrayKey::Int(0), &key("01")], array.keys().collect::<Vec<_>>());
+    assert_eq!(Some(&PhpVal::Long(2)), array.get(0));
+
+    let array: PhpArray = vec![(key("-1"), PhpVal::Long(1))].into_iter().collect();
+    assert_eq!(true, array.contains_key(-1));
+    let array = PhpArray::from_php_val(PhpVal::Array(vec![(key("1"), PhpVal::Long(1))])).unwrap();
+    assert_eq!(vec![&ArrayKey::Int(1)], array.keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn array_key_ref_into_owned() {
+    assert_eq!(key("foo"), ArrayKey::from(ArrayKeyRef::String("foo")));
+    assert_eq!(ArrayKey::Int(-1), ArrayKeyRef::Int(-1).into());
+}
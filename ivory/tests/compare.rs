@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+
+use pretty_assertions::assert_eq;
+
+use ivory::{ArrayKey, CompareMode, PhpVal};
+
+fn string(value: &str) -> PhpVal {
+    PhpVal::String(value.to_string())
+}
+
+fn array(items: Vec<(ArrayKey, PhpVal)>) -> PhpVal {
+    PhpVal::Array(items)
+}
+
+fn key(key: &str) -> ArrayKey {
+    ArrayKey::String(key.to_string())
+}
+
+#[test]
+fn compare_loose_eq() {
+    let equal = vec![
+        (string("1e3"), string("1000")),
+        (string("1"), string("01")),
+        (string("10"), string("1e1")),
+        (string(" 1"), string("1")),
+        (string("1 "), string("1")),
+        (PhpVal::Long(100), string("1e2")),
+        (PhpVal::Long(1), PhpVal::Double(1.0)),
+        (PhpVal::Null, PhpVal::Bool(false)),
+        (PhpVal::Null, string("")),
+        (PhpVal::Null, PhpVal::Array(vec![])),
+        (PhpVal::Null, PhpVal::Long(0)),
+        (PhpVal::Bool(true), string("abc")),
+        (PhpVal::Bool(false), string("0")),
+        (
+            array(vec![(key("a"), PhpVal::Long(1)), (key("b"), PhpVal::Long(2))]),
+            array(vec![(key("b"), string("2")), (key("a"), PhpVal::Long(1))]),
+        ),
+    ];
+    for (a, b) in equal {
+        assert_eq!(true, a.loose_eq(&b), "{:?} == {:?}", a, b);
+        assert_eq!(true, b.loose_eq(&a), "{:?} == {:?}", b, a);
+    }
+
+    let not_equal = vec![
+        (string("abc"), PhpVal::Long(0)),
+        (string(""), PhpVal::Long(0)),
+        (string("1abc"), PhpVal::Long(1)),
+        (string("abc"), string("ABC")),
+        (string("1e3"), string("1e3 apples")),
+        (PhpVal::Null, string("0")),
+        (PhpVal::Double(std::f64::NAN), PhpVal::Double(std::f64::NAN)),
+        // integers overflowing into the same float are compared as strings
+        (string("9223372036854775808"), string("9223372036854775809")),
+        (array(vec![(key("a"), PhpVal::Long(1))]), array(vec![(key("b"), PhpVal::Long(1))])),
+    ];
+    for (a, b) in not_equal {
+        assert_eq!(false, a.loose_eq(&b), "{:?} == {:?}", a, b);
+        assert_eq!(false, b.loose_eq(&a), "{:?} == {:?}", b, a);
+    }
+}
+
+#[test]
+fn compare_php7() {
+    let php7 = CompareMode::Php7;
+    assert_eq!(true, string("abc").loose_eq_with_mode(&PhpVal::Long(0), php7));
+    assert_eq!(true, string("").loose_eq_with_mode(&PhpVal::Long(0), php7));
+    assert_eq!(true, string("1abc").loose_eq_with_mode(&PhpVal::Long(1), php7));
+    assert_eq!(false, string("1 ").loose_eq_with_mode(&string("1"), php7));
+    assert_eq!(true, string("1e3").loose_eq_with_mode(&string("1000"), php7));
+    assert_eq!(
+        Ordering::Less,
+        string("abc").spaceship_with_mode(&PhpVal::Long(1), php7)
+    );
+    assert_eq!(Ordering::Greater, string("abc").spaceship(&PhpVal::Long(1)));
+}
+
+#[test]
+fn compare_strict_eq() {
+    assert_eq!(true, PhpVal::Long(1).strict_eq(&PhpVal::Long(1)));
+    assert_eq!(false, PhpVal::Long(1).strict_eq(&PhpVal::Double(1.0)));
+    assert_eq!(false, string("1e3").strict_eq(&string("1000")));
+    assert_eq!(true, PhpVal::Null.strict_eq(&PhpVal::Null));
+    assert_eq!(false, PhpVal::Null.strict_eq(&PhpVal::Bool(false)));
+    assert_eq!(false, PhpVal::Double(std::f64::NAN).strict_eq(&PhpVal::Double(std::f64::NAN)));
+    assert_eq!(
+        true,
+        PhpVal::Reference(Box::new(PhpVal::Long(1))).strict_eq(&PhpVal::Long(1))
+    );
+
+    let a = array(vec![(key("a"), PhpVal::Long(1)), (key("b"), PhpVal::Long(2))]);
+    let b = array(vec![(key("b"), PhpVal::Long(2)), (key("a"), PhpVal::Long(1))]);
+    assert_eq!(true, a.strict_eq(&a.clone()));
+    assert_eq!(false, a.strict_eq(&b));
+    assert_eq!(true, a.loose_eq(&b));
+}
+
+#[test]
+fn compare_spaceship() {
+    assert_eq!(Ordering::Less, string("abc").spaceship(&string("abd")));
+    assert_eq!(Ordering::Less, string("9").spaceship(&string("10")));
+    assert_eq!(Ordering::Greater, string("9").spaceship(&string("10a")));
+    assert_eq!(Ordering::Less, PhpVal::Long(5).spaceship(&PhpVal::Double(5.5)));
+    assert_eq!(Ordering::Less, PhpVal::Null.spaceship(&PhpVal::Bool(true)));
+    assert_eq!(Ordering::Greater, PhpVal::Array(vec![]).spaceship(&PhpVal::Long(5)));
+    assert_eq!(Ordering::Less, PhpVal::Long(5).spaceship(&PhpVal::Array(vec![])));
+
+    // arrays are compared by size first, then by value
+    let short = PhpVal::from(vec![5, 6]);
+    let long = PhpVal::from(vec![1, 2, 3]);
+    assert_eq!(Ordering::Greater, long.spaceship(&short));
+    assert_eq!(
+        Ordering::Less,
+        PhpVal::from(vec![1, 2]).spaceship(&PhpVal::from(vec![1, 3]))
+    );
+    // arrays with different keys are uncomparable
+    let a = array(vec![(key("a"), PhpVal::Long(1))]);
+    let b = array(vec![(key("b"), PhpVal::Long(1))]);
+    assert_eq!(Ordering::Greater, a.spaceship(&b));
+    assert_eq!(Ordering::Greater, b.spaceship(&a));
+}
+
+#[test]
+fn compare_sort_unique() {
+    let mut values = vec![string("10"), PhpVal::Long(9), string("9a"), PhpVal::Double(1.5)];
+    values.sort_by(|a, b| a.spaceship(b));
+    assert_eq!(
+        vec![PhpVal::Double(1.5), PhpVal::Long(9), string("10"), string("9a")],
+        values
+    );
+
+    // like array_unique, keep the first of each group of loosely equal values
+    let values = vec![PhpVal::Long(1), string("1"), string("01"), PhpVal::Long(2)];
+    let mut unique: Vec<PhpVal> = Vec::new();
+    for value in values {
+        if !unique.iter().any(|seen| seen.loose_eq(&value)) {
+            unique.push(value);
+        }
+    }
+    assert_eq!(vec![PhpVal::Long(1), PhpVal::Long(2)], unique);
+}
@@ -0,0 +1,299 @@
+use pretty_assertions::assert_eq;
+
+use ivory::zend::{PhpConstants, ZValType};
+use ivory::{ArrayKey, CastError, FromPhp, FromPhpVal, IndexMap, IntoPhp, PhpVal};
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+struct Address {
+    city: String,
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+struct User {
+    name: String,
+    #[php(rename = "user_age")]
+    age: i64,
+    email: Option<String>,
+    #[php(default)]
+    tags: Vec<String>,
+    #[php(skip)]
+    cached: bool,
+    address: Address,
+}
+
+fn key(key: &str) -> ArrayKey {
+    ArrayKey::String(key.to_string())
+}
+
+fn user_array(address: PhpVal) -> Vec<(ArrayKey, PhpVal)> {
+    vec![
+        (key("name"), PhpVal::String("Alice".to_string())),
+        (key("user_age"), PhpVal::Long(30)),
+        (key("address"), address),
+    ]
+}
+
+#[test]
+fn derive_from_php() {
+    let address = PhpVal::Array(vec![(key("city"), PhpVal::String("Paris".to_string()))]);
+    let user = User::from_php_val(PhpVal::Array(user_array(address))).unwrap();
+    assert_eq!(
+        User {
+            name: "Alice".to_string(),
+            age: 30,
+            email: None,
+            tags: vec![],
+            cached: false,
+            address: Address {
+                city: "Paris".to_string()
+            },
+        },
+        user
+    );
+}
+
+#[test]
+fn derive_into_php() {
+    let user = User {
+        name: "Alice".to_string(),
+        age: 30,
+        email: Some("alice@example.com".to_string()),
+        tags: vec!["admin".to_string()],
+        cached: true,
+        address: Address {
+            city: "Paris".to_string(),
+        },
+    };
+    assert_eq!(
+        PhpVal::Array(vec![
+            (key("name"), PhpVal::String("Alice".to_string())),
+            (key("user_age"), PhpVal::Long(30)),
+            (key("email"), PhpVal::String("alice@example.com".to_string())),
+            (
+                key("tags"),
+                PhpVal::Array(vec![(ArrayKey::Int(0), PhpVal::String("admin".to_string()))])
+            ),
+            (
+                key("address"),
+                PhpVal::Array(vec![(key("city"), PhpVal::String("Paris".to_string()))])
+            ),
+        ]),
+        user.into()
+    );
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+#[php(object)]
+struct Size {
+    width: i64,
+    height: i64,
+}
+
+#[test]
+fn derive_object() {
+    let size: PhpVal = Size { width: 2, height: 3 }.into();
+    let properties = match size {
+        PhpVal::Object(properties) => properties,
+        val => panic!("expected an object, got {:?}", val),
+    };
+    let names: Vec<&str> = properties.keys().map(String::as_str).collect();
+    assert_eq!(vec!["width", "height"], names);
+
+    let mut object = IndexMap::new();
+    object.insert("height".to_string(), PhpVal::Long(3));
+    object.insert("width".to_string(), PhpVal::Long(2));
+    assert_eq!(Size { width: 2, height: 3 }, Size::from_php_val(PhpVal::Object(object)).unwrap());
+}
+
+#[test]
+fn derive_error_path() {
+    let err: CastError =
+        User::from_php_val(PhpVal::Array(user_array(PhpVal::Array(vec![])))).unwrap_err();
+    assert_eq!(vec![key("address"), key("city")], err.path);
+    assert_eq!("Missing value at [\"address\"][\"city\"]", format!("{}", err));
+
+    let address = PhpVal::Array(vec![(key("city"), PhpVal::Long(1))]);
+    let err = User::from_php_val(PhpVal::Array(user_array(address))).unwrap_err();
+    assert_eq!(
+        "Incorrect variable type at [\"address\"][\"city\"], got long",
+        format!("{}", err)
+    );
+
+    let err = User::from_php_val(PhpVal::Long(1)).unwrap_err();
+    assert_eq!("Incorrect variable type, got long", format!("{}", err));
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum Mode {
+    #[php(value = "fast")]
+    Fast,
+    #[php(value = "accurate")]
+    Accurate,
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum Level {
+    #[php(value = 1)]
+    Low,
+    #[php(value = 2)]
+    High,
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum Direction {
+    #[php(value = -1)]
+    Down,
+    #[php(value = 1)]
+    Up,
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum Bound {
+    #[php(value = -9223372036854775808)]
+    Min,
+    #[php(value = 9223372036854775807)]
+    Max,
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+#[php(constants)]
+enum HTTPStatus {
+    NotFound,
+    Found,
+    XMLHttpError,
+}
+
+#[test]
+fn derive_enum() {
+    assert_eq!(
+        Mode::Accurate,
+        Mode::from_php_val(PhpVal::String("accurate".to_string())).unwrap()
+    );
+    assert_eq!(Level::High, Level::from_php_val(PhpVal::Long(2)).unwrap());
+    assert_eq!(PhpVal::String("fast".to_string()), Mode::Fast.into());
+    assert_eq!(PhpVal::Long(1), Level::Low.into());
+    assert_eq!(
+        Direction::Down,
+        Direction::from_php_val(PhpVal::Long(-1)).unwrap()
+    );
+    assert_eq!(PhpVal::Long(-1), Direction::Down.into());
+    assert_eq!(Bound::Min, Bound::from_php_val(PhpVal::Long(i64::min_value())).unwrap());
+    assert_eq!(PhpVal::Long(i64::max_value()), Bound::Max.into());
+}
+
+#[test]
+fn derive_enum_constants() {
+    let names: Vec<String> = HTTPStatus::constants()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(
+        vec![
+            "HTTP_STATUS_NOT_FOUND",
+            "HTTP_STATUS_FOUND",
+            "HTTP_STATUS_XML_HTTP_ERROR"
+        ],
+        names
+    );
+}
+
+#[test]
+fn derive_enum_errors() {
+    let err = Mode::from_php_val(PhpVal::String("slow".to_string())).unwrap_err();
+    assert_eq!(
+        vec![PhpVal::String("fast".to_string()), PhpVal::String("accurate".to_string())],
+        err.allowed
+    );
+    assert_eq!(
+        "Invalid value, expected one of \"fast\", \"accurate\"",
+        format!("{}", err)
+    );
+    let err = Level::from_php_val(PhpVal::Long(3)).unwrap_err();
+    assert_eq!("Invalid value, expected one of 1, 2", format!("{}", err));
+    let err = Mode::from_php_val(PhpVal::Long(1)).unwrap_err();
+    assert_eq!("Incorrect variable type, got long", format!("{}", err));
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum IdOrName {
+    Id(i64),
+    Name(String),
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum RatioOrCount {
+    Ratio(f64),
+    Count(i64),
+}
+
+#[derive(Debug, PartialEq, FromPhp, IntoPhp)]
+enum ModeOrAddress {
+    Mode(Mode),
+    Address(Address),
+}
+
+#[test]
+fn derive_union() {
+    assert_eq!(IdOrName::Id(1), IdOrName::from_php_val(PhpVal::Long(1)).unwrap());
+    assert_eq!(
+        IdOrName::Name("foo".to_string()),
+        IdOrName::from_php_val(PhpVal::String("foo".to_string())).unwrap()
+    );
+    assert_eq!(PhpVal::Long(1), IdOrName::Id(1).into());
+
+    let err = IdOrName::from_php_val(PhpVal::Array(vec![])).unwrap_err();
+    assert_eq!(vec![ZValType::Long, ZValType::String], err.expected);
+    assert_eq!("Value must be of type int|string, array given", format!("{}", err));
+    assert_eq!(&[ZValType::Long, ZValType::String], IdOrName::UNION_TYPES);
+    assert_eq!(false, IdOrName::ALLOW_NULL);
+    assert_eq!(&[ZValType::Long, ZValType::String], <Option<IdOrName>>::UNION_TYPES);
+
+    // ints are accepted for floats but a variant of the exact type is preferred
+    assert_eq!(
+        RatioOrCount::Count(2),
+        RatioOrCount::from_php_val(PhpVal::Long(2)).unwrap()
+    );
+    assert_eq!(
+        RatioOrCount::Ratio(0.5),
+        RatioOrCount::from_php_val(PhpVal::Double(0.5)).unwrap()
+    );
+}
+
+#[test]
+fn derive_union_content_error() {
+    // errors of a variant matching the type are reported instead of the type mismatch
+    let err = ModeOrAddress::from_php_val(PhpVal::String("slow".to_string())).unwrap_err();
+    assert_eq!(
+        "Invalid value, expected one of \"fast\", \"accurate\"",
+        format!("{}", err)
+    );
+    let err = ModeOrAddress::from_php_val(PhpVal::Array(vec![])).unwrap_err();
+    assert_eq!("Missing value at [\"city\"]", format!("{}", err));
+    let err = ModeOrAddress::from_php_val(PhpVal::Long(1)).unwrap_err();
+    assert_eq!("Value must be of type string|array, int given", format!("{}", err));
+}
+
+#[test]
+fn derive_coerce() {
+    let address = PhpVal::Array(vec![(key("city"), PhpVal::Long(1))]);
+    let array = PhpVal::Array(vec![
+        (key("name"), PhpVal::String("Alice".to_string())),
+        (key("user_age"), PhpVal::String("30".to_string())),
+        (key("address"), address),
+    ]);
+    assert_eq!(true, User::from_php_val(array.clone()).is_err());
+    let user = User::coerce_php_val(array).unwrap();
+    assert_eq!(30, user.age);
+    assert_eq!("1", user.address.city);
+
+    assert_eq!(Level::High, Level::coerce_php_val(PhpVal::String("2".to_string())).unwrap());
+    assert_eq!(true, Level::from_php_val(PhpVal::String("2".to_string())).is_err());
+
+    // exact type matches are preferred over coercion
+    assert_eq!(
+        IdOrName::Name("2".to_string()),
+        IdOrName::coerce_php_val(PhpVal::String("2".to_string())).unwrap()
+    );
+    assert_eq!(IdOrName::Id(1), IdOrName::coerce_php_val(PhpVal::Bool(true)).unwrap());
+    assert_eq!(true, IdOrName::from_php_val(PhpVal::Bool(true)).is_err());
+}
@@ -0,0 +1,75 @@
+#![cfg(php81)]
+
+use pretty_assertions::assert_eq;
+
+use ivory::zend::{PhpEnum, ZValType};
+use ivory::{ivory_enum, FromPhpVal, PhpVal};
+
+#[ivory_enum]
+#[derive(Debug, PartialEq)]
+enum Status {
+    Active,
+    Archived,
+}
+
+#[ivory_enum]
+#[derive(Debug, PartialEq)]
+enum Suit {
+    #[php(value = "H")]
+    Hearts,
+    #[php(value = "S")]
+    Spades,
+}
+
+#[ivory_enum]
+#[derive(Debug, PartialEq)]
+enum Priority {
+    #[php(value = -1)]
+    Low,
+    #[php(value = 10)]
+    High,
+}
+
+fn case(class: &str, case: &str) -> PhpVal {
+    PhpVal::EnumCase {
+        class: class.to_string(),
+        case: case.to_string(),
+    }
+}
+
+#[test]
+fn enum_cases() {
+    assert_eq!("Status", Status::NAME);
+    assert_eq!(vec![("Active", None), ("Archived", None)], Status::cases());
+    assert_eq!(
+        vec![
+            ("Hearts", Some(PhpVal::String("H".to_string()))),
+            ("Spades", Some(PhpVal::String("S".to_string())))
+        ],
+        Suit::cases()
+    );
+    assert_eq!(
+        vec![("Low", Some(PhpVal::Long(-1))), ("High", Some(PhpVal::Long(10)))],
+        Priority::cases()
+    );
+}
+
+#[test]
+fn enum_conversion() {
+    assert_eq!(Suit::Spades, Suit::from_php_val(case("Suit", "Spades")).unwrap());
+    assert_eq!(Suit::Hearts, Suit::from_php_val(case("suit", "Hearts")).unwrap());
+    assert_eq!(case("Status", "Archived"), Status::Archived.into());
+    assert_eq!(ZValType::Object, Suit::TYPE_HINT);
+    assert_eq!("Suit\0", Suit::CLASS_NAME);
+}
+
+#[test]
+fn enum_conversion_errors() {
+    // the backing value isn't accepted in place of the case
+    let err = Suit::from_php_val(PhpVal::String("H".to_string())).unwrap_err();
+    assert_eq!("Value must be of type Suit, string given", format!("{}", err));
+    let err = Suit::from_php_val(case("Status", "Active")).unwrap_err();
+    assert_eq!("Value must be of type Suit, object given", format!("{}", err));
+    let err = Suit::from_php_val(case("Suit", "Clubs")).unwrap_err();
+    assert_eq!("Invalid value, Clubs is not a case of enum Suit", format!("{}", err));
+}
@@ -0,0 +1,77 @@
+use pretty_assertions::assert_eq;
+
+use std::mem::{self, size_of};
+
+use ivory::zend::{ExecuteData, ZVal, ZValType};
+use ivory_sys::{zend_execute_data, zval};
+
+/// A call frame followed by its arguments, laid out the same way the engine does
+struct Frame {
+    slots: Vec<zval>,
+}
+
+impl Frame {
+    fn new(args: Vec<i64>) -> Self {
+        // the arguments start at the first zval slot after the execute data
+        let offset = (size_of::<zend_execute_data>() + size_of::<zval>() - 1) / size_of::<zval>();
+        let mut slots: Vec<zval> = (0..offset + args.len() + 1)
+            .map(|_| unsafe { mem::zeroed() })
+            .collect();
+        for (i, arg) in args.iter().enumerate() {
+            slots[offset + i] = long(*arg);
+        }
+        // a trailing value that isn't part of the arguments
+        slots[offset + args.len()] = long(-1);
+
+        let data = slots.as_mut_ptr() as *mut zend_execute_data;
+        unsafe { (*data).This.u2.num_args = args.len() as u32 };
+        Frame { slots }
+    }
+
+    fn data(&self) -> &ExecuteData {
+        unsafe { &*(self.slots.as_ptr() as *const ExecuteData) }
+    }
+}
+
+fn long(value: i64) -> zval {
+    let mut zval: zval = unsafe { mem::zeroed() };
+    zval.value.lval = value;
+    zval.u1.type_info = ZValType::Long.code() as u32;
+    zval
+}
+
+#[test]
+fn execute_data_arg_bounds() {
+    let frame = Frame::new(vec![1, 2]);
+    let data = frame.data();
+    assert_eq!(2, data.num_args());
+    assert_eq!(Some(1), data.arg(0).map(|arg| unsafe { arg.as_i64() }));
+    assert_eq!(Some(2), data.arg(1).map(|arg| unsafe { arg.as_i64() }));
+    assert_eq!(true, data.arg(2).is_none());
+    assert_eq!(true, data.arg(u32::max_value()).is_none());
+
+    let frame = Frame::new(vec![]);
+    assert_eq!(true, frame.data().arg(0).is_none());
+}
+
+#[test]
+fn execute_data_args_len() {
+    let frame = Frame::new(vec![1, 2, 3]);
+    let mut args = frame.data().args();
+    assert_eq!(3, args.len());
+    args.next();
+    assert_eq!(2, args.len());
+    assert_eq!(vec![2, 3], args.map(|arg| unsafe { arg.as_i64() }).collect::<Vec<_>>());
+
+    let frame = Frame::new(vec![1, 2, 3]);
+    let args = frame.data().args().skip(1);
+    assert_eq!(2, args.len());
+    assert_eq!(
+        vec![ZValType::Long, ZValType::Long],
+        args.map(ZVal::get_type).collect::<Vec<_>>()
+    );
+
+    let frame = Frame::new(vec![]);
+    assert_eq!(0, frame.data().args().len());
+    assert_eq!(None, frame.data().args().next().map(|arg| arg.get_type()));
+}
@@ -0,0 +1,102 @@
+use pretty_assertions::assert_eq;
+
+use ivory::{ArrayKey, PhpVal};
+
+fn string(value: &str) -> PhpVal {
+    PhpVal::String(value.to_string())
+}
+
+#[test]
+fn juggle_bool() {
+    assert_eq!(false, PhpVal::Null.to_bool());
+    assert_eq!(false, PhpVal::Long(0).to_bool());
+    assert_eq!(true, PhpVal::Long(-1).to_bool());
+    assert_eq!(false, PhpVal::Double(-0.0).to_bool());
+    assert_eq!(true, PhpVal::Double(std::f64::NAN).to_bool());
+    assert_eq!(false, string("").to_bool());
+    assert_eq!(false, string("0").to_bool());
+    assert_eq!(true, string("0.0").to_bool());
+    assert_eq!(true, string(" ").to_bool());
+    assert_eq!(false, PhpVal::Array(vec![]).to_bool());
+    assert_eq!(true, PhpVal::Array(vec![(ArrayKey::Int(0), PhpVal::Null)]).to_bool());
+    assert_eq!(false, PhpVal::Reference(Box::new(PhpVal::Bool(false))).to_bool());
+}
+
+#[test]
+fn juggle_long() {
+    assert_eq!(0, PhpVal::Null.to_long());
+    assert_eq!(1, PhpVal::Bool(true).to_long());
+    assert_eq!(1, PhpVal::Double(1.9).to_long());
+    assert_eq!(-1, PhpVal::Double(-1.9).to_long());
+    assert_eq!(0, PhpVal::Double(std::f64::NAN).to_long());
+    assert_eq!(0, PhpVal::Double(std::f64::INFINITY).to_long());
+    // out of range floats wrap around
+    assert_eq!(0, PhpVal::Double(18_446_744_073_709_551_616.0).to_long());
+    assert_eq!(i64::min_value(), PhpVal::Double(9_223_372_036_854_775_808.0).to_long());
+
+    assert_eq!(12, string("12").to_long());
+    assert_eq!(12, string("  12abc").to_long());
+    assert_eq!(-3, string("-3.9").to_long());
+    assert_eq!(1000, string("1e3").to_long());
+    assert_eq!(0, string("abc").to_long());
+    assert_eq!(0, string("0x1A").to_long());
+    // out of range strings saturate
+    assert_eq!(i64::max_value(), string("99999999999999999999").to_long());
+    assert_eq!(i64::min_value(), string("-1e30").to_long());
+
+    assert_eq!(0, PhpVal::Array(vec![]).to_long());
+    assert_eq!(3, PhpVal::Resource(3).to_long());
+}
+
+#[test]
+fn juggle_double() {
+    assert_eq!(0.0, PhpVal::Null.to_double());
+    assert_eq!(1.0, PhpVal::Bool(true).to_double());
+    assert_eq!(2.0, PhpVal::Long(2).to_double());
+    assert_eq!(1.5, string("1.5 apples").to_double());
+    assert_eq!(0.5, string(".5").to_double());
+    assert_eq!(1e30, string("1e30").to_double());
+    assert_eq!(0.0, string("apples").to_double());
+}
+
+#[test]
+fn juggle_string() {
+    assert_eq!("", PhpVal::Null.to_php_string());
+    assert_eq!("1", PhpVal::Bool(true).to_php_string());
+    assert_eq!("", PhpVal::Bool(false).to_php_string());
+    assert_eq!("-5", PhpVal::Long(-5).to_php_string());
+    assert_eq!("1", PhpVal::Double(1.0).to_php_string());
+    assert_eq!("0.1", PhpVal::Double(0.1).to_php_string());
+    assert_eq!("0.3", PhpVal::Double(0.1 + 0.2).to_php_string());
+    assert_eq!("3.1415926535898", PhpVal::Double(std::f64::consts::PI).to_php_string());
+    assert_eq!("0.0001", PhpVal::Double(0.0001).to_php_string());
+    assert_eq!("1.0E-5", PhpVal::Double(0.00001).to_php_string());
+    assert_eq!("1.0E+25", PhpVal::Double(1e25).to_php_string());
+    assert_eq!("-1.5E+14", PhpVal::Double(-1.5e14).to_php_string());
+    assert_eq!("-0", PhpVal::Double(-0.0).to_php_string());
+    assert_eq!("INF", PhpVal::Double(std::f64::INFINITY).to_php_string());
+    assert_eq!("NAN", PhpVal::Double(std::f64::NAN).to_php_string());
+    assert_eq!("Array", PhpVal::Array(vec![]).to_php_string());
+    assert_eq!("Resource id #2", PhpVal::Resource(2).to_php_string());
+
+    assert_eq!(
+        "0.30000000000000004",
+        PhpVal::Double(0.1 + 0.2).to_php_string_with_precision(17)
+    );
+    assert_eq!("3.14", PhpVal::Double(std::f64::consts::PI).to_php_string_with_precision(3));
+    assert_eq!("1.0E+3", PhpVal::Double(1234.0).to_php_string_with_precision(1));
+}
+
+#[test]
+fn juggle_is_numeric() {
+    for numeric in &["1", "-1", "+1.5", ".5", "5.", "1e3", "1E-3", " 1", "1 ", "\t\n1\n"] {
+        assert_eq!(true, string(numeric).is_numeric(), "{:?}", numeric);
+    }
+    for not_numeric in &["", " ", ".", "-", "1e", "1 1", "abc", "1abc", "0x1A", "1_000", "e3"] {
+        assert_eq!(false, string(not_numeric).is_numeric(), "{:?}", not_numeric);
+    }
+    assert_eq!(true, PhpVal::Long(1).is_numeric());
+    assert_eq!(true, PhpVal::Double(std::f64::NAN).is_numeric());
+    assert_eq!(false, PhpVal::Bool(true).is_numeric());
+    assert_eq!(false, PhpVal::Null.is_numeric());
+}
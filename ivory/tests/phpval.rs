@@ -2,8 +2,11 @@
 use maplit::hashmap;
 use pretty_assertions::assert_eq;
 
-use ivory::zend::ZVal;
-use ivory::{ArrayKey, PhpVal};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+use ivory::zend::{ZVal, ZValType};
+use ivory::{ArrayKey, CastError, FromPhpVal, Nullable, Optional, PhpVal};
 
 #[test]
 fn cast_into_php_val() {
@@ -82,6 +85,7 @@ fn cast_into_php_val() {
     );
 }
 
+/// Only values that don't need the engine's memory manager, see the php integration tests for the others
 #[test]
 fn cast_into_php_val_round_trip() {
     let values: Vec<PhpVal> = vec![
@@ -89,7 +93,6 @@ fn cast_into_php_val_round_trip() {
         (0.2).into(),
         true.into(),
         false.into(),
-        "foo".to_string().into(),
     ];
 
     for original in values {
@@ -103,3 +106,224 @@ fn cast_into_php_val_round_trip() {
         assert_eq!(original, cast_back);
     }
 }
+
+#[test]
+fn clone_zval() {
+    let values: Vec<PhpVal> = vec![1.into(), (0.2).into(), true.into(), PhpVal::Null];
+
+    for original in values {
+        let zval: ZVal = original.clone().into();
+        assert_eq!(None, zval.refcount());
+        let cloned = zval.clone();
+        drop(zval);
+        assert_eq!(original, cloned.as_php_val());
+    }
+
+    // strings and arrays are allocated by the engine's memory manager,
+    // they are tested in the php integration tests
+}
+
+#[test]
+fn cast_reference() {
+    let reference = PhpVal::Reference(Box::new(PhpVal::Long(1)));
+    let result: Result<i64, _> = reference.clone().into();
+    assert_eq!(1, result.unwrap());
+    let result: Result<Option<i64>, _> = reference.clone().into();
+    assert_eq!(Some(1), result.unwrap());
+    let result: Result<String, _> = reference.clone().into();
+    assert_eq!(true, result.is_err());
+
+    let zval: ZVal = reference.into();
+    assert_eq!(PhpVal::Long(1), zval.as_php_val());
+}
+
+#[test]
+fn zval_type_codes() {
+    for code in 0..=u8::max_value() {
+        if let Ok(ty) = ZValType::try_from(code) {
+            assert_eq!(code, u8::from(ty));
+        }
+    }
+    assert_eq!(ZValType::Array, ZValType::try_from(7).unwrap());
+    assert_eq!(true, ZValType::try_from(42).is_err());
+    assert_eq!(42, ZValType::Unknown(42).code());
+}
+
+#[test]
+fn cast_array_arguments() {
+    let list = PhpVal::Array(vec![
+        (ArrayKey::Int(0), PhpVal::Long(1)),
+        (ArrayKey::Int(1), PhpVal::Long(2)),
+    ]);
+    let result: Result<Vec<i64>, _> = list.clone().into();
+    assert_eq!(vec![1, 2], result.unwrap());
+    let result: Result<Option<Vec<i64>>, _> = PhpVal::Null.into();
+    assert_eq!(None, result.unwrap());
+
+    let nested = PhpVal::Array(vec![
+        (ArrayKey::String("foo".to_string()), list.clone()),
+        (ArrayKey::String("bar".to_string()), PhpVal::Array(vec![])),
+    ]);
+    let result: Result<BTreeMap<String, Vec<i64>>, _> = nested.clone().into();
+    let mut expected = BTreeMap::new();
+    expected.insert("foo".to_string(), vec![1, 2]);
+    expected.insert("bar".to_string(), vec![]);
+    assert_eq!(expected, result.unwrap());
+
+    let result: Result<HashMap<String, PhpVal>, _> = nested.clone().into();
+    assert_eq!(2, result.unwrap().len());
+
+    let result: Result<HashMap<i64, Vec<i64>>, _> = nested.into();
+    assert_eq!(true, result.is_err());
+
+    let result: Result<HashMap<String, i64>, _> = list.into();
+    assert_eq!(Some(&2), result.unwrap().get("1"));
+}
+
+#[test]
+fn cast_array_error_path() {
+    let nested = PhpVal::Array(vec![(
+        ArrayKey::String("foo".to_string()),
+        PhpVal::Array(vec![
+            (ArrayKey::Int(0), PhpVal::Long(1)),
+            (ArrayKey::Int(1), PhpVal::String("bar".to_string())),
+        ]),
+    )]);
+    let result: Result<BTreeMap<String, Vec<i64>>, CastError> = nested.into();
+    let err = result.unwrap_err();
+    assert_eq!(
+        vec![ArrayKey::String("foo".to_string()), ArrayKey::Int(1)],
+        err.path
+    );
+    assert_eq!(
+        "Incorrect variable type at [\"foo\"][1], got string",
+        format!("{}", err)
+    );
+
+    let result: Result<Vec<i64>, CastError> = PhpVal::Long(1).into();
+    assert_eq!("Incorrect variable type, got long", format!("{}", result.unwrap_err()));
+}
+
+#[test]
+fn cast_optional_nullable() {
+    assert_eq!(Optional::Omitted, Optional::<i64>::from_php_val(PhpVal::Undef).unwrap());
+    assert_eq!(Optional::Given(1), Optional::<i64>::from_php_val(PhpVal::Long(1)).unwrap());
+    assert_eq!(true, Optional::<i64>::from_php_val(PhpVal::Null).is_err());
+
+    assert_eq!(Nullable::Null, Nullable::<i64>::from_php_val(PhpVal::Null).unwrap());
+    assert_eq!(Nullable::Value(1), Nullable::<i64>::from_php_val(PhpVal::Long(1)).unwrap());
+    assert_eq!(true, Nullable::<i64>::from_php_val(PhpVal::Undef).is_err());
+
+    type Both = Optional<Nullable<i64>>;
+    assert_eq!(Optional::Omitted, Both::from_php_val(PhpVal::Undef).unwrap());
+    assert_eq!(Optional::Given(Nullable::Null), Both::from_php_val(PhpVal::Null).unwrap());
+    assert_eq!(false, <Optional<i64> as FromPhpVal>::ALLOW_NULL);
+    assert_eq!(true, <Both as FromPhpVal>::ALLOW_NULL);
+}
+
+#[test]
+fn cast_int_range() {
+    assert_eq!(255u8, u8::from_php_val(PhpVal::Long(255)).unwrap());
+    let err = u8::from_php_val(PhpVal::Long(300)).unwrap_err();
+    assert_eq!("Invalid value, 300 is out of range for u8", format!("{}", err));
+    assert_eq!(true, u64::from_php_val(PhpVal::Long(-1)).is_err());
+    assert_eq!(true, i8::from_php_val(PhpVal::Long(-129)).is_err());
+    assert_eq!(-128i8, i8::from_php_val(PhpVal::Long(-128)).unwrap());
+
+    assert_eq!(PhpVal::Long(i64::max_value()), (i64::max_value() as u64).into());
+    assert_eq!(PhpVal::Double(u64::max_value() as f64), u64::max_value().into());
+    assert_eq!(PhpVal::Long(255), 255u8.into());
+}
+
+fn string(value: &str) -> PhpVal {
+    PhpVal::String(value.to_string())
+}
+
+#[test]
+fn coerce_scalars() {
+    assert_eq!(5, i64::coerce_php_val(string("5")).unwrap());
+    assert_eq!(5, i64::coerce_php_val(string(" 5")).unwrap());
+    assert_eq!(1000, i64::coerce_php_val(string("1e3")).unwrap());
+    assert_eq!(2, i64::coerce_php_val(PhpVal::Double(2.0)).unwrap());
+    assert_eq!(1, i64::coerce_php_val(PhpVal::Bool(true)).unwrap());
+    assert_eq!(true, i64::coerce_php_val(string("5 apples")).is_err());
+    assert_eq!(true, i64::coerce_php_val(PhpVal::Null).is_err());
+    let err = i64::coerce_php_val(PhpVal::Double(1.5)).unwrap_err();
+    assert_eq!(
+        "Invalid value, 1.5 can't be converted to an int without losing precision",
+        format!("{}", err)
+    );
+    let err = i64::coerce_php_val(PhpVal::Double(1e20)).unwrap_err();
+    assert_eq!("Invalid value, 1.0E+20 is out of range for an int", format!("{}", err));
+    assert_eq!(true, u8::coerce_php_val(string("300")).is_err());
+
+    assert_eq!(1.5, f64::coerce_php_val(string("1.5")).unwrap());
+    assert_eq!(2.0, f64::coerce_php_val(PhpVal::Long(2)).unwrap());
+    assert_eq!(".5e1".parse::<f64>().unwrap(), f64::coerce_php_val(string(".5e1")).unwrap());
+
+    assert_eq!("1", String::coerce_php_val(PhpVal::Long(1)).unwrap());
+    assert_eq!("0.1", String::coerce_php_val(PhpVal::Double(0.1)).unwrap());
+    assert_eq!("0.3", String::coerce_php_val(PhpVal::Double(0.1 + 0.2)).unwrap());
+    assert_eq!("-1.5E-7", String::coerce_php_val(PhpVal::Double(-1.5e-7)).unwrap());
+    assert_eq!("1.0E+15", String::coerce_php_val(PhpVal::Double(1e15)).unwrap());
+    assert_eq!("10000000000000", String::coerce_php_val(PhpVal::Double(1e13)).unwrap());
+    assert_eq!("", String::coerce_php_val(PhpVal::Bool(false)).unwrap());
+
+    assert_eq!(false, bool::coerce_php_val(string("0")).unwrap());
+    assert_eq!(true, bool::coerce_php_val(string("0.0")).unwrap());
+    assert_eq!(false, bool::coerce_php_val(PhpVal::Double(0.0)).unwrap());
+    assert_eq!(true, bool::coerce_php_val(PhpVal::Array(vec![])).is_err());
+}
+
+#[test]
+fn coerce_wrappers() {
+    assert_eq!(
+        vec![1.0, 2.0],
+        Vec::<f64>::coerce_php_val(PhpVal::from(vec![PhpVal::Long(1), string("2")])).unwrap()
+    );
+    // strict typing still accepts ints for floats
+    assert_eq!(vec![1.0], Vec::<f64>::from_php_val(PhpVal::from(vec![PhpVal::Long(1)])).unwrap());
+    assert_eq!(true, Vec::<f64>::from_php_val(PhpVal::from(vec![string("2")])).is_err());
+    assert_eq!(Some(3), Option::<i64>::coerce_php_val(string("3")).unwrap());
+    assert_eq!(None, Option::<i64>::coerce_php_val(PhpVal::Null).unwrap());
+    assert_eq!(Nullable::Value(3), Nullable::<i64>::coerce_php_val(string("3")).unwrap());
+    assert_eq!(Optional::Given(3), Optional::<i64>::coerce_php_val(string("3")).unwrap());
+    assert_eq!(true, Optional::<i64>::coerce_php_val(PhpVal::Null).is_err());
+}
+
+#[test]
+fn array_key_normalize() {
+    assert_eq!(ArrayKey::Int(5), ArrayKey::from("5"));
+    assert_eq!(ArrayKey::Int(-1), ArrayKey::from("-1".to_string()));
+    assert_eq!(ArrayKey::Int(0), ArrayKey::from("0"));
+    assert_eq!(ArrayKey::Int(i64::min_value()), ArrayKey::from("-9223372036854775808"));
+    for key in &["05", "+5", "-0", "5 ", " 5", "1e3", "1.5", "", "9223372036854775808"] {
+        assert_eq!(ArrayKey::String(key.to_string()), ArrayKey::from(*key));
+    }
+    assert_eq!(ArrayKey::Int(-1), ArrayKey::from(-1));
+    assert_eq!(ArrayKey::Int(-1), ArrayKey::from(u64::max_value()));
+
+    assert_eq!(
+        PhpVal::Array(vec![
+            (ArrayKey::Int(5), PhpVal::Long(1)),
+            (ArrayKey::String("05".to_string()), PhpVal::Long(2)),
+        ]),
+        vec![("5".to_string(), 1), ("05".to_string(), 2)].into()
+    );
+    let keys: HashMap<i64, String> = hashmap! { -1 => "x".to_string() };
+    assert_eq!(
+        PhpVal::Array(vec![(ArrayKey::Int(-1), PhpVal::String("x".to_string()))]),
+        keys.into()
+    );
+}
+
+#[test]
+fn cast_negative_keys() {
+    let val = PhpVal::Array(vec![(ArrayKey::Int(-1), PhpVal::Long(1))]);
+    let map: BTreeMap<i64, i64> = BTreeMap::from_php_val(val.clone()).unwrap();
+    assert_eq!(Some(&1), map.get(&-1));
+    let map: BTreeMap<String, i64> = BTreeMap::from_php_val(val.clone()).unwrap();
+    assert_eq!(Some(&1), map.get("-1"));
+    let err = BTreeMap::<u64, i64>::from_php_val(val).unwrap_err();
+    assert_eq!("Invalid value at [-1], -1 is out of range for u64", format!("{}", err));
+}
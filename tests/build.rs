@@ -0,0 +1,14 @@
+use std::env;
+
+fn main() {
+    // reported by the build script of ivory-sys from the php headers, enums are only tested on php 8.1
+    let version_id: u32 = env::var("DEP_PHP7_VERSION_ID")
+        .expect("ivory-sys didn't report the php version")
+        .parse()
+        .expect("invalid php version");
+
+    println!("cargo:rustc-check-cfg=cfg(php81)");
+    if version_id >= 80100 {
+        println!("cargo:rustc-cfg=php81");
+    }
+}
@@ -1,9 +1,17 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::imported::imported_fn;
 use ivory::externs::printf;
-use ivory::{ivory_export, ivory_module};
-use ivory::{ArrayKey, PhpVal};
+use ivory::{ivory_export, ivory_module, output, php_print, php_println};
+use std::io::Write;
+use ivory::zend::{ZArray, ZHashTable, ZVal, ZValType};
+use ivory::{
+    ArrayKey, ArrayKeyRef, CastError, FromPhp, FromPhpVal, IntoPhp, IntoPhpVal, Nullable, Optional, PhpArray, PhpRef,
+    PhpVal, Resource, UnregisteredResourceError, Variadic,
+};
 
 mod imported;
 
@@ -66,6 +74,26 @@ fn return_string() -> String {
     "some string data".to_string()
 }
 
+/// The refcounts of a zval created from the value while it's cloned and dropped
+#[ivory_export]
+fn zval_clone_refcounts(value: PhpVal) -> Vec<Option<u32>> {
+    let zval = ZVal::from(value);
+    let mut refcounts = vec![zval.refcount()];
+    let cloned = zval.clone();
+    refcounts.push(zval.refcount());
+    refcounts.push(cloned.refcount());
+    drop(zval);
+    refcounts.push(cloned.refcount());
+    refcounts
+}
+
+/// Convert a value into a zval and back twice
+#[ivory_export]
+fn zval_round_trip(value: PhpVal) -> PhpVal {
+    let first = ZVal::from(value).as_php_val();
+    ZVal::from(first).as_php_val()
+}
+
 #[ivory_export]
 fn return_array_simple() -> Vec<i32> {
     vec![-10, 10, 0]
@@ -90,6 +118,380 @@ fn return_array_nested() -> Vec<Vec<i32>> {
     vec![vec![1, 2], vec![3, 4]]
 }
 
+#[ivory_export]
+fn variadic_sum(values: Variadic<i64>) -> i64 {
+    values.iter().sum()
+}
+
+#[ivory_export]
+fn variadic_join(glue: String, parts: Variadic<String>) -> String {
+    parts.join(&glue)
+}
+
+#[ivory_export]
+fn default_limit(#[default = 10] limit: i64) -> i64 {
+    limit
+}
+
+#[ivory_export]
+fn default_greeting(name: String, #[default = "Hello"] greeting: String) -> String {
+    format!("{} {}", greeting, name)
+}
+
+#[ivory_export]
+fn double_byte(value: u8) -> u16 {
+    u16::from(value) * 2
+}
+
+#[ivory_export]
+fn max_u64() -> u64 {
+    u64::max_value()
+}
+
+#[ivory_export]
+fn optional_limit(limit: Optional<i64>) -> i64 {
+    limit.unwrap_or(-1)
+}
+
+#[ivory_export]
+fn nullable_limit(#[default = 5] limit: Nullable<i64>) -> Option<i64> {
+    limit.into_option()
+}
+
+#[ivory_export]
+fn describe_limit(limit: Optional<Nullable<i64>>) -> String {
+    match limit {
+        Optional::Omitted => "omitted".to_string(),
+        Optional::Given(Nullable::Null) => "null".to_string(),
+        Optional::Given(Nullable::Value(limit)) => format!("limit {}", limit),
+    }
+}
+
+#[ivory_export]
+fn increment(value: &mut PhpRef<i64>) {
+    **value += 1;
+}
+
+#[ivory_export]
+fn split_words(input: String, words: &mut PhpRef<Option<Vec<String>>>) -> bool {
+    words.set(Some(input.split(' ').map(String::from).collect()));
+    true
+}
+
+#[ivory_export]
+fn match_words(input: String, matches: &mut PhpRef<Vec<String>>) -> bool {
+    let words: Vec<String> = input.split_whitespace().map(String::from).collect();
+    let found = !words.is_empty();
+    matches.set(words);
+    found
+}
+
+#[ivory_export]
+fn array_sum_ints(values: Vec<i64>) -> i64 {
+    values.into_iter().sum()
+}
+
+#[ivory_export]
+fn matrix_total(rows: Vec<Vec<f64>>) -> f64 {
+    rows.into_iter().flatten().sum()
+}
+
+#[ivory_export]
+fn group_sizes(groups: BTreeMap<String, Vec<String>>) -> BTreeMap<String, usize> {
+    groups
+        .into_iter()
+        .map(|(name, members)| (name, members.len()))
+        .collect()
+}
+
+#[ivory_export]
+fn header_value(headers: HashMap<String, String>, name: String) -> Option<String> {
+    headers.get(&name).cloned()
+}
+
+#[ivory_export]
+fn array_append(array: PhpArray, value: PhpVal) -> PhpArray {
+    let mut array = array;
+    array.push(value);
+    array
+}
+
+#[ivory_export]
+fn array_view_get(array: ZArray, key: String) -> Option<i64> {
+    array.get(key).and_then(|val| val.cast(true).ok())
+}
+
+#[ivory_export]
+fn array_view_keys(array: ZArray) -> String {
+    let keys: Vec<String> = array
+        .iter()
+        .map(|(key, _)| match key {
+            ArrayKeyRef::Int(index) => index.to_string(),
+            ArrayKeyRef::String(name) => name.to_string(),
+        })
+        .collect();
+    format!("{}: {}", array.len(), keys.join(","))
+}
+
+#[ivory_export]
+fn hash_table_fill(table: &mut ZHashTable) {
+    table.remove(0);
+    table.update("updated", 1);
+    table.insert("kept", 2);
+    table.push(3);
+}
+
+#[ivory_export]
+fn hash_table_sort(table: &mut ZHashTable, renumber: bool) {
+    table.sort_by(|a, b| a.as_php_val().spaceship(&b.as_php_val()), renumber);
+}
+
+#[ivory_export]
+fn hash_table_push(table: &mut ZHashTable, value: i64) -> Option<i64> {
+    table.push(value)
+}
+
+#[ivory_export]
+fn hash_table_sort_panicking(table: &mut ZHashTable) {
+    table.sort_by(|_, _| panic!("inconsistent comparison"), false);
+}
+
+#[ivory_export]
+fn hash_table_build() -> ZHashTable {
+    let mut table = ZHashTable::new();
+    table.push("discarded".to_string());
+    table.clear();
+    table.push(1);
+    table.update("name", "ivory".to_string());
+    table
+}
+
+struct GeoPoint {
+    lat: f64,
+    lon: f64,
+}
+
+impl FromPhpVal for GeoPoint {
+    const TYPE_HINT: ZValType = ZValType::Array;
+
+    fn from_php_val(val: PhpVal) -> Result<Self, CastError> {
+        match Vec::<f64>::from_php_val(val)?.as_slice() {
+            [lat, lon] => Ok(GeoPoint {
+                lat: *lat,
+                lon: *lon,
+            }),
+            _ => Err(CastError::new(ZValType::Array)),
+        }
+    }
+}
+
+impl IntoPhpVal for GeoPoint {
+    fn into_php_val(self) -> PhpVal {
+        vec![self.lat, self.lon].into()
+    }
+}
+
+#[ivory_export]
+fn geo_midpoint(a: GeoPoint, b: GeoPoint) -> GeoPoint {
+    GeoPoint {
+        lat: (a.lat + b.lat) / 2.0,
+        lon: (a.lon + b.lon) / 2.0,
+    }
+}
+
+#[derive(FromPhp, IntoPhp)]
+struct Address {
+    city: String,
+}
+
+#[derive(FromPhp, IntoPhp)]
+struct User {
+    name: String,
+    #[php(rename = "user_age")]
+    age: i64,
+    email: Option<String>,
+    #[php(default)]
+    tags: Vec<String>,
+    address: Address,
+}
+
+#[ivory_export]
+fn user_birthday(user: User) -> User {
+    User {
+        age: user.age + 1,
+        ..user
+    }
+}
+
+#[derive(FromPhp, IntoPhp)]
+#[php(object)]
+struct Size {
+    width: i64,
+    height: i64,
+}
+
+#[ivory_export]
+fn size_double(size: Size) -> Size {
+    Size {
+        width: size.width * 2,
+        height: size.height * 2,
+    }
+}
+
+#[derive(FromPhp, IntoPhp)]
+#[php(constants)]
+enum Mode {
+    #[php(value = "fast")]
+    Fast,
+    #[php(value = "accurate")]
+    Accurate,
+}
+
+#[derive(FromPhp, IntoPhp)]
+#[php(constants = "PRIORITY_")]
+enum Priority {
+    #[php(value = 1)]
+    Low,
+    #[php(value = 5)]
+    High,
+}
+
+#[derive(FromPhp, IntoPhp)]
+#[php(constants)]
+enum Separator {
+    #[php(value = "\0")]
+    Nul,
+    #[php(value = ",")]
+    Comma,
+}
+
+#[ivory_export]
+fn separator_name(separator: Separator) -> String {
+    match separator {
+        Separator::Nul => "nul".to_string(),
+        Separator::Comma => "comma".to_string(),
+    }
+}
+
+#[ivory_export]
+fn throw_message(message: String) {
+    ivory::externs::throw_error(message);
+}
+
+#[ivory_export]
+fn toggle_mode(mode: Mode) -> Mode {
+    match mode {
+        Mode::Fast => Mode::Accurate,
+        Mode::Accurate => Mode::Fast,
+    }
+}
+
+#[ivory_export]
+fn priority_name(priority: Priority) -> String {
+    match priority {
+        Priority::Low => "low".to_string(),
+        Priority::High => "high".to_string(),
+    }
+}
+
+#[derive(FromPhp, IntoPhp)]
+enum IdOrName {
+    Id(i64),
+    Name(String),
+}
+
+#[ivory_export]
+fn describe_user(user: IdOrName) -> String {
+    match user {
+        IdOrName::Id(id) => format!("user #{}", id),
+        IdOrName::Name(name) => format!("user {}", name),
+    }
+}
+
+#[cfg(php81)]
+#[ivory::ivory_enum]
+enum Suit {
+    #[php(value = "H")]
+    Hearts,
+    #[php(value = "S")]
+    Spades,
+}
+
+#[cfg(php81)]
+#[ivory_export]
+fn flip_suit(suit: Suit) -> Suit {
+    match suit {
+        Suit::Hearts => Suit::Spades,
+        Suit::Spades => Suit::Hearts,
+    }
+}
+
+#[cfg(php81)]
+#[ivory_export]
+fn suit_name(suit: Option<Suit>) -> String {
+    match suit {
+        Some(Suit::Hearts) => "hearts".to_string(),
+        Some(Suit::Spades) => "spades".to_string(),
+        None => "none".to_string(),
+    }
+}
+
+static COUNTERS_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct Counter {
+    count: Cell<i64>,
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        COUNTERS_DROPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[ivory_export]
+fn counter_new(start: i64) -> Result<Resource<Counter>, UnregisteredResourceError> {
+    Resource::new(Counter {
+        count: Cell::new(start),
+    })
+}
+
+#[ivory_export]
+fn counter_increment(counter: &Resource<Counter>) -> i64 {
+    counter.count.set(counter.count.get() + 1);
+    counter.count.get()
+}
+
+#[ivory_export]
+fn counters_dropped() -> usize {
+    COUNTERS_DROPPED.load(Ordering::SeqCst)
+}
+
+#[ivory_export]
+fn print_raw(input: String) {
+    printf(input);
+}
+
+#[ivory_export]
+fn print_formatted(name: String, count: i64) {
+    php_print!("{}: ", name);
+    php_println!("{}", count);
+}
+
+#[ivory_export]
+fn print_bytes() {
+    output().write_all(b"a\0b").unwrap();
+}
+
+#[ivory_export]
+fn log_warning(message: String) {
+    log::warn!("{}", message);
+}
+
+#[ivory_export]
+fn stubs() -> String {
+    php_stubs()
+}
+
 ivory_module!({
     name: "tests",
     version: "0.0.1",
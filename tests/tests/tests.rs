@@ -32,6 +32,11 @@ fn zval_parsing() {
             (ArrayKey::from(1u64), 2),
             (ArrayKey::from("foo".to_string()), 3)
         ].into(),
+        "[-1 => 1, \"5\" => 2, \"05\" => 3]" => vec![
+            (ArrayKey::from(-1), 1),
+            (ArrayKey::from(5), 2),
+            (ArrayKey::from("05"), 3)
+        ].into(),
     };
 
     for (input, expected) in inputs {
@@ -42,7 +47,7 @@ fn zval_parsing() {
 }
 
 macro_rules! test_cast {
-    ($name:ident, $method:expr, $in:expr, $fail:expr) => {
+    ($name:ident, $method:expr, $in:expr, $fail:expr, $coerced:expr, $coerced_value:expr) => {
         #[test]
         fn $name() {
             let result = run_php(&format!("{}({})", $method, $in)).unwrap();
@@ -50,20 +55,72 @@ macro_rules! test_cast {
             assert_eq!(true, run_php(&format!("{}({})", $method, $fail)).is_err());
             assert_eq!(true, run_php(&format!("{}(null)", $method)).is_err());
             assert_eq!(true, run_php(&format!("{}()", $method)).is_err());
+            // coercive typing unless the caller declared strict types
+            let result = run_php(&format!("{}({})", $method, $coerced)).unwrap();
+            assert_debug_eq($coerced_value, &result);
+            let strict = format!("declare(strict_types=1); {}({})", $method, $coerced);
+            assert_eq!(true, run_php(&strict).is_err());
         }
     };
 }
 
-test_cast!(test_cast_long, "expect_long", 1, false);
-test_cast!(test_cast_double, "expect_double", 1.1, false);
-test_cast!(test_cast_string, "expect_string", "foo".to_string(), false);
-test_cast!(test_cast_bool, "expect_bool", true, 17);
+test_cast!(test_cast_long, "expect_long", 1, "'foo'", "'5'", 5);
+test_cast!(test_cast_double, "expect_double", 1.1, "[]", "'1'", 1.0);
+test_cast!(test_cast_string, "expect_string", "foo".to_string(), "[]", "1.5", "1.5".to_string());
+test_cast!(test_cast_bool, "expect_bool", true, "[]", "17", true);
+
+#[test]
+fn test_zval_refcount() {
+    assert_eq!("[1,2,2,1]", run_php("echo json_encode(zval_clone_refcounts('foo'))").unwrap());
+    assert_eq!("[1,2,2,1]", run_php("echo json_encode(zval_clone_refcounts(['bar']))").unwrap());
+    assert_eq!(
+        "[null,null,null,null]",
+        run_php("echo json_encode(zval_clone_refcounts(1))").unwrap()
+    );
+    assert_eq!(
+        "'foo'",
+        run_php("var_export(zval_round_trip('foo'))").unwrap()
+    );
+    assert_eq!(
+        "true",
+        run_php("$a = ['x' => [1, 'bar']]; var_export(zval_round_trip($a) === $a)").unwrap()
+    );
+    assert_eq!(
+        "true",
+        run_php("$f = fopen('php://memory', 'r'); var_export(zval_round_trip([$f]) === [$f])").unwrap()
+    );
+    // the resource is kept alive by the returned value
+    assert_eq!(
+        "'stream'",
+        run_php("$f = zval_round_trip(fopen('php://memory', 'r')); var_export(get_resource_type($f))").unwrap()
+    );
+}
+
+#[test]
+fn test_coercion() {
+    assert_debug_eq(5.0, &run_php("declare(strict_types=1); expect_double(5)").unwrap());
+    // stricter than php 7.3, which truncates floats, uses leading numbers and converts null
+    assert_eq!(true, run_php("expect_long(1.5)").is_err());
+    assert_eq!(true, run_php("expect_long('1 apple')").is_err());
+    assert_eq!(true, run_php("expect_double('1.5 apples')").is_err());
+    assert_eq!(true, run_php("expect_string(null)").is_err());
+    assert_eq!(true, run_php("expect_bool(null)").is_err());
+    assert_debug_eq(1, &run_php("expect_long(true)").unwrap());
+    assert_debug_eq("1.0E+25", &run_php("expect_string(1e25)").unwrap());
+    assert_debug_eq(
+        "foo",
+        &run_php("class Foo { function __toString() { return 'foo'; } } expect_string(new Foo())").unwrap(),
+    );
+    assert_eq!(true, run_php("expect_string(new stdClass())").is_err());
+    assert_eq!("float(3)\n", run_php("var_dump(matrix_total([[1], ['2']]))").unwrap());
+}
 
 #[test]
 fn test_cast_option() {
     let result = run_php("expect_option_bool(true)").unwrap();
     assert_debug_eq(Some(true), &result);
-    assert_eq!(true, run_php("expect_option_bool(17)").is_err());
+    assert_eq!(true, run_php("expect_option_bool([])").is_err());
+    assert_eq!(true, run_php("declare(strict_types=1); expect_option_bool(17)").is_err());
     let result = run_php("expect_option_bool(null)").unwrap();
     assert_debug_eq::<Option<bool>>(None, &result);
     let result = run_php("expect_option_bool()").unwrap();
@@ -111,6 +168,406 @@ test_return!(
     "[[1,2],[3,4]]"
 );
 
+#[test]
+fn test_variadic() {
+    assert_eq!("int(6)\n", run_php("var_dump(variadic_sum(1, 2, 3))").unwrap());
+    assert_eq!("int(0)\n", run_php("var_dump(variadic_sum())").unwrap());
+    assert_eq!(true, run_php("variadic_sum(1, \"foo\")").is_err());
+    assert_eq!(
+        "a-b-c",
+        run_php("echo variadic_join(\"-\", \"a\", \"b\", \"c\")").unwrap()
+    );
+    assert_eq!(true, run_php("variadic_join()").is_err());
+    assert_eq!(
+        "true",
+        run_php("echo json_encode((new ReflectionFunction('variadic_join'))->isVariadic())").unwrap()
+    );
+}
+
+#[test]
+fn test_default() {
+    assert_eq!("int(10)\n", run_php("var_dump(default_limit())").unwrap());
+    assert_eq!("int(3)\n", run_php("var_dump(default_limit(3))").unwrap());
+    assert_eq!(true, run_php("default_limit(\"foo\")").is_err());
+    assert_eq!(
+        "Hello world",
+        run_php("echo default_greeting(\"world\")").unwrap()
+    );
+    assert_eq!(
+        "Hi world",
+        run_php("echo default_greeting(\"world\", \"Hi\")").unwrap()
+    );
+    assert_eq!(
+        "1",
+        run_php("echo (new ReflectionFunction('default_greeting'))->getNumberOfRequiredParameters()")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_stubs() {
+    let stubs = run_php("echo stubs()").unwrap();
+    assert_eq!(true, stubs.starts_with("<?php\n"));
+    for declaration in &[
+        "function default_limit(int $limit = 10) {}",
+        "function default_greeting(string $name, string $greeting = 'Hello') {}",
+        "function nullable_limit(?int $limit = 5) {}",
+        "function optional_limit(int $limit = UNKNOWN) {}",
+        "function describe_limit(?int $limit = null) {}",
+        "function variadic_join(string $glue, ...$parts) {}",
+        "function expect_long(int $arg) {}",
+        "function describe_user(int|string $user) {}",
+    ] {
+        assert_eq!(true, stubs.contains(declaration), "{} not found in {}", declaration, stubs);
+    }
+}
+
+#[test]
+fn test_int_range() {
+    assert_eq!("int(510)\n", run_php("var_dump(double_byte(255))").unwrap());
+    assert_eq!(true, run_php("double_byte(300)").is_err());
+    assert_eq!(true, run_php("double_byte(-1)").is_err());
+    assert_eq!(
+        "out of range",
+        run_php("try { double_byte(300); } catch (TypeError $e) { echo 'out of range'; }").unwrap()
+    );
+    assert_eq!("float(1.8446744073709552E+19)\n", run_php("var_dump(max_u64())").unwrap());
+}
+
+#[test]
+fn test_optional_nullable() {
+    assert_eq!("int(-1)\n", run_php("var_dump(optional_limit())").unwrap());
+    assert_eq!("int(3)\n", run_php("var_dump(optional_limit(3))").unwrap());
+    assert_eq!(true, run_php("optional_limit(null)").is_err());
+    assert_eq!("int(5)\n", run_php("var_dump(nullable_limit())").unwrap());
+    assert_eq!("NULL\n", run_php("var_dump(nullable_limit(null))").unwrap());
+    assert_eq!("omitted", run_php("echo describe_limit()").unwrap());
+    assert_eq!("null", run_php("echo describe_limit(null)").unwrap());
+    assert_eq!("limit 2", run_php("echo describe_limit(2)").unwrap());
+    assert_eq!(
+        "falsetrue",
+        run_php("$p = (new ReflectionFunction('optional_limit'))->getParameters()[0]; echo json_encode($p->allowsNull()), json_encode($p->isOptional())")
+            .unwrap()
+    );
+    assert_eq!(
+        "truetrue",
+        run_php("$p = (new ReflectionFunction('nullable_limit'))->getParameters()[0]; echo json_encode($p->allowsNull()), json_encode($p->isOptional())")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_by_reference() {
+    assert_eq!(
+        "int(2)\n",
+        run_php("$a = 1; increment($a); var_dump($a)").unwrap()
+    );
+    assert_eq!(true, run_php("$a = 'foo'; increment($a)").is_err());
+    // the current value is converted with the caller's typing mode
+    assert_eq!(
+        "int(2)\n",
+        run_php("$a = '1'; increment($a); var_dump($a)").unwrap()
+    );
+    assert_eq!(
+        true,
+        run_php("declare(strict_types=1); $a = '1'; increment($a)").is_err()
+    );
+    // variables without a value are filled in like preg_match does
+    assert_eq!(
+        "[\"foo\",\"bar\"]",
+        run_php("match_words('foo bar', $matches); echo json_encode($matches)").unwrap()
+    );
+    assert_eq!(
+        "[]",
+        run_php("$matches = null; match_words('', $matches); echo json_encode($matches)").unwrap()
+    );
+    assert_eq!(
+        "[\"baz\"]",
+        run_php("$matches = ['foo']; match_words('baz', $matches); echo json_encode($matches)").unwrap()
+    );
+    assert_eq!(
+        "[\"foo\",\"bar\"]",
+        run_php("split_words('foo bar', $words); echo json_encode($words)").unwrap()
+    );
+    assert_eq!(
+        "true",
+        run_php("echo json_encode((new ReflectionFunction('increment'))->getParameters()[0]->isPassedByReference())")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_array_arguments() {
+    assert_eq!("int(6)\n", run_php("var_dump(array_sum_ints([1, 2, 3]))").unwrap());
+    assert_eq!("int(0)\n", run_php("var_dump(array_sum_ints([]))").unwrap());
+    assert_eq!(true, run_php("array_sum_ints([1, 'foo'])").is_err());
+    assert_eq!(true, run_php("array_sum_ints(1)").is_err());
+    assert_eq!(
+        "float(10.5)\n",
+        run_php("var_dump(matrix_total([[1.5, 2.0], [3.0, 4.0]]))").unwrap()
+    );
+    // strict types still accept ints for floats
+    assert_eq!(
+        "float(3.5)\n",
+        run_php("declare(strict_types=1); var_dump(matrix_total([[1.5], [2]]))").unwrap()
+    );
+    assert_eq!(true, run_php("declare(strict_types=1); matrix_total([[1.5], ['2']])").is_err());
+    assert_eq!(
+        "{\"admins\":1,\"users\":2}",
+        run_php("echo json_encode(group_sizes(['users' => ['a', 'b'], 'admins' => ['c']]))")
+            .unwrap()
+    );
+    assert_eq!(
+        "text/plain",
+        run_php("echo header_value(['Content-Type' => 'text/plain'], 'Content-Type')").unwrap()
+    );
+    assert_eq!(
+        "{\"5\":\"a\",\"foo\":\"b\",\"6\":\"c\"}",
+        run_php("echo json_encode(array_append([5 => 'a', 'foo' => 'b'], 'c'))").unwrap()
+    );
+    assert_eq!(
+        "array",
+        run_php("echo (new ReflectionFunction('array_sum_ints'))->getParameters()[0]->getType()")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_array_view() {
+    assert_eq!("2", run_php("echo array_view_get(['a' => 1, 5 => 2], '5')").unwrap());
+    assert_eq!("1", run_php("echo array_view_get(['a' => 1, 5 => 2], 'a')").unwrap());
+    assert_eq!("NULL\n", run_php("var_dump(array_view_get(['a' => 1], 'b'))").unwrap());
+    assert_eq!(
+        "2: 0,2",
+        run_php("$a = [1, 2, 3]; unset($a[1]); echo array_view_keys($a)").unwrap()
+    );
+    assert_eq!("0: ", run_php("echo array_view_keys([])").unwrap());
+    assert_eq!(
+        "2: y,x",
+        run_php("$a = ['x' => 1, 'y' => 2]; unset($a['x']); $a['x'] = 3; echo array_view_keys($a)").unwrap()
+    );
+    assert_eq!(true, run_php("array_view_keys(1)").is_err());
+    assert_eq!(
+        "array",
+        run_php("echo (new ReflectionFunction('array_view_keys'))->getParameters()[0]->getType()")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_hash_table() {
+    assert_eq!(
+        r#"{"kept":"old","updated":1,"1":3} {"0":"x","kept":"old"}"#,
+        run_php("$a = ['x', 'kept' => 'old']; $b = $a; hash_table_fill($a); echo json_encode($a), ' ', json_encode($b);")
+            .unwrap()
+    );
+    assert_eq!(
+        "[1,2,3] [3,1,2]",
+        run_php("$a = [3, 1, 2]; $b = $a; hash_table_sort($a, true); echo json_encode($a), ' ', json_encode($b);")
+            .unwrap()
+    );
+    assert_eq!(
+        r#"{"a":1,"c":2}"#,
+        run_php("$a = ['c' => 2, 'b' => 3, 'a' => 1]; unset($a['b']); hash_table_sort($a, false); echo json_encode($a);")
+            .unwrap()
+    );
+    assert_eq!(r#"{"0":1,"name":"ivory"}"#, run_php("echo json_encode(hash_table_build())").unwrap());
+    assert_eq!(
+        "int(6)\nint(9223372036854775807)\nNULL\n",
+        run_php(
+            "$a = [5 => 'x']; var_dump(hash_table_push($a, 1)); \
+             $a = [PHP_INT_MAX - 1 => 'x']; var_dump(hash_table_push($a, 1)); var_dump(hash_table_push($a, 2));"
+        )
+        .unwrap()
+    );
+    // a panic in the comparison function is thrown instead of aborting php
+    assert_eq!(
+        "Sorting failed: inconsistent comparison 3",
+        run_php(
+            "$a = [3, 1, 2]; \
+             try { hash_table_sort_panicking($a); } catch (Error $e) { echo $e->getMessage(), ' ', count($a); }"
+        )
+        .unwrap()
+    );
+    assert_eq!(true, run_php("$a = 1; hash_table_fill($a)").is_err());
+    assert_eq!(
+        "true",
+        run_php("echo var_export((new ReflectionFunction('hash_table_fill'))->getParameters()[0]->isPassedByReference())")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_custom_conversion() {
+    assert_eq!(
+        "[1.5,2.5]",
+        run_php("echo json_encode(geo_midpoint([1.0, 2.0], [2.0, 3.0]))").unwrap()
+    );
+    assert_eq!(true, run_php("geo_midpoint([1.0], [2.0, 3.0])").is_err());
+    assert_eq!(
+        "array",
+        run_php("echo (new ReflectionFunction('geo_midpoint'))->getParameters()[0]->getType()")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_derive() {
+    assert_eq!(
+        r#"{"name":"Alice","user_age":31,"email":null,"tags":[],"address":{"city":"Paris"}}"#,
+        run_php(r#"echo json_encode(user_birthday(["name" => "Alice", "user_age" => 30, "address" => ["city" => "Paris"]]))"#)
+            .unwrap()
+    );
+    assert_eq!(
+        true,
+        run_php(r#"user_birthday(["name" => "Alice", "user_age" => 30, "address" => []])"#).is_err()
+    );
+    assert_eq!(
+        r#"{"name":"Alice","user_age":31,"email":null,"tags":[],"address":{"city":"Paris"}}"#,
+        run_php(r#"echo json_encode(user_birthday((object)["name" => "Alice", "user_age" => 30, "address" => (object)["city" => "Paris"]]))"#)
+            .unwrap()
+    );
+    assert_eq!(
+        r#"stdClass {"width":4,"height":6}"#,
+        run_php("$size = size_double((object)['width' => 2, 'height' => 3]); echo get_class($size), ' ', json_encode($size);")
+            .unwrap()
+    );
+    // only public properties are visible, like reading them from outside the class
+    assert_eq!(
+        true,
+        run_php("class Rect { private $width = 1; public $height = 2; } size_double(new Rect());").is_err()
+    );
+    assert_eq!(
+        r#"{"width":2,"height":4}"#,
+        run_php("class Rect { public $width = 1; public $height = 2; private $area = 2; } echo json_encode(size_double(new Rect()));")
+            .unwrap()
+    );
+    assert_eq!(
+        "array (\n  0 => 'width',\n  1 => 'height',\n)",
+        run_php("class Rect { public $width = 1; protected $area = 2; public $height = 2; private $name = 'r'; } var_export(array_keys((array) zval_round_trip(new Rect())));")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_enum() {
+    assert_eq!("accurate", run_php("echo toggle_mode('fast')").unwrap());
+    assert_eq!("fast", run_php("echo toggle_mode(MODE_ACCURATE)").unwrap());
+    assert_eq!(true, run_php("toggle_mode('slow')").is_err());
+    assert_eq!("high", run_php("echo priority_name(5)").unwrap());
+    assert_eq!("high", run_php("echo priority_name(PRIORITY_HIGH)").unwrap());
+    assert_eq!(true, run_php("priority_name(2)").is_err());
+}
+
+#[cfg(php81)]
+#[test]
+fn test_native_enum() {
+    assert_eq!("true", run_php("var_export(Suit::Hearts instanceof UnitEnum)").unwrap());
+    assert_eq!("true", run_php("var_export(flip_suit(Suit::Hearts) === Suit::Spades)").unwrap());
+    assert_eq!("S", run_php("echo flip_suit(Suit::from('H'))->value").unwrap());
+    assert_eq!("none", run_php("echo suit_name(null)").unwrap());
+    assert_eq!("spades", run_php("echo suit_name(Suit::Spades)").unwrap());
+    assert_eq!("NULL", run_php("var_export(Suit::tryFrom('X'))").unwrap());
+    let err = run_php("flip_suit('H')").unwrap_err();
+    assert_eq!(true, err.contains("TypeError"), "{}", err);
+    assert_eq!(true, err.contains("must be of type Suit, string given"), "{}", err);
+    let err = run_php("Suit::from('X')").unwrap_err();
+    assert_eq!(true, err.contains("ValueError"), "{}", err);
+    assert_eq!(
+        "Suit",
+        run_php("echo (new ReflectionFunction('flip_suit'))->getParameters()[0]->getType()").unwrap()
+    );
+
+    let stubs = run_php("echo stubs()").unwrap();
+    for declaration in &[
+        "enum Suit: string\n{\n    case Hearts = 'H';\n    case Spades = 'S';\n}",
+        "function flip_suit(Suit $suit) {}",
+        "function suit_name(?Suit $suit = null) {}",
+    ] {
+        assert_eq!(true, stubs.contains(declaration), "{} not found in {}", declaration, stubs);
+    }
+}
+
+#[test]
+fn test_nul_bytes() {
+    assert_eq!("1", run_php("echo strlen(SEPARATOR_NUL)").unwrap());
+    assert_eq!("nul", run_php("echo separator_name(\"\\0\")").unwrap());
+    assert_eq!("comma", run_php("echo separator_name(SEPARATOR_COMMA)").unwrap());
+    assert_eq!(
+        "foobar",
+        run_php("try { throw_message(\"foo\\0bar\"); } catch (Error $e) { echo $e->getMessage(); }").unwrap()
+    );
+}
+
+#[test]
+fn test_union() {
+    assert_eq!("user #1", run_php("echo describe_user(1)").unwrap());
+    assert_eq!("user bob", run_php("echo describe_user('bob')").unwrap());
+    assert_eq!(true, run_php("describe_user([])").is_err());
+}
+
+#[test]
+fn test_array_reference() {
+    let result = run_php("$x = 1; $a = [&$x]; dump_arg($a)").unwrap();
+    assert_debug_eq(
+        PhpVal::Array(vec![(
+            ArrayKey::Int(0),
+            PhpVal::Reference(Box::new(PhpVal::Long(1))),
+        )]),
+        &result,
+    );
+    assert_eq!(
+        "int(1)\n",
+        run_php("$x = 1; $a = [&$x]; var_dump(variadic_sum(...$a))").unwrap()
+    );
+}
+
+#[test]
+fn test_resource() {
+    assert_eq!(
+        "7",
+        run_php("$c = counter_new(5); counter_increment($c); echo counter_increment($c)").unwrap()
+    );
+    assert_eq!(
+        "Counter",
+        run_php("echo get_resource_type(counter_new(1))").unwrap()
+    );
+    assert_eq!(
+        "01",
+        run_php("$c = counter_new(1); echo counters_dropped(); unset($c); echo counters_dropped()")
+            .unwrap()
+    );
+    assert_eq!(true, run_php("counter_increment(1)").is_err());
+    assert_eq!(
+        true,
+        run_php("counter_increment(fopen('php://memory', 'r'))").is_err()
+    );
+}
+
+#[test]
+fn test_output() {
+    assert_eq!("100%s%n", run_php("print_raw('100%s%n')").unwrap());
+    assert_eq!("foo: 3\n", run_php("print_formatted('foo', 3)").unwrap());
+    assert_eq!("a\0b", run_php("print_bytes()").unwrap());
+    assert_eq!(
+        "FOO: 3\n",
+        run_php("ob_start(); print_formatted('foo', 3); echo strtoupper(ob_get_clean())").unwrap()
+    );
+}
+
+#[test]
+fn test_log() {
+    assert_eq!(true, run_php("log_warning('foo')").unwrap().contains("tests: foo"));
+    assert_eq!(
+        "",
+        run_php("ini_set('tests.log_warn', 'off'); log_warning('foo')").unwrap()
+    );
+    assert_eq!("log", run_php("echo ini_get('tests.log_info')").unwrap());
+    let output = run_php("ini_set('tests.log_warn', 'error'); log_warning('foo %s %n'); echo 'done';").unwrap();
+    assert_eq!(true, output.contains("tests: foo %s %n"));
+    assert_eq!(true, output.ends_with("done"));
+}
+
 #[test]
 fn test_imported() {
     assert_eq!("imported".to_string(), run_php("imported_fn()").unwrap());